@@ -30,6 +30,7 @@
 
 use failure::Fail;
 use heck::SnakeCase;
+use mimir::PlaceKind;
 use rs_es::error::EsError;
 use serde::{Deserialize, Serialize};
 use slog_scope::error;
@@ -47,6 +48,14 @@ pub enum BragiError {
     Es(EsError),
     #[fail(display = "invalid shape: {}", _0)]
     InvalidShape(&'static str),
+    #[fail(display = "store error: {}", _0)]
+    Store(failure::Error),
+    #[fail(display = "the zones are not loaded, bragi must be started with --load-zones")]
+    ZonesNotLoaded,
+    #[fail(display = "unknown ranking profile {}", _0)]
+    UnknownProfile(String),
+    #[fail(display = "invalid batch: {}", _0)]
+    InvalidBatch(String),
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -72,6 +81,14 @@ impl actix_web::error::ResponseError for BragiError {
                 short: "validation error".to_owned(),
                 long: format!("{}", self),
             }),
+            BragiError::UnknownProfile(_) => actix_web::HttpResponse::BadRequest().json(ApiError {
+                short: "validation error".to_owned(),
+                long: format!("{}", self),
+            }),
+            BragiError::InvalidBatch(_) => actix_web::HttpResponse::BadRequest().json(ApiError {
+                short: "validation error".to_owned(),
+                long: format!("{}", self),
+            }),
             BragiError::IndexNotFound => actix_web::HttpResponse::NotFound().json(ApiError {
                 short: "query error".to_owned(),
                 long: format!("{}", self),
@@ -91,6 +108,19 @@ impl actix_web::error::ResponseError for BragiError {
                     }),
                 }
             }
+            BragiError::ZonesNotLoaded => {
+                actix_web::HttpResponse::NotImplemented().json(ApiError {
+                    short: "query error".to_owned(),
+                    long: format!("{}", self),
+                })
+            }
+            BragiError::Store(ref err) => {
+                error!("store error on query: {}", &err);
+                actix_web::HttpResponse::InternalServerError().json(ApiError {
+                    short: "query error".to_owned(),
+                    long: "internal server error".to_owned(),
+                })
+            }
         }
     }
 }
@@ -101,10 +131,35 @@ impl From<EsError> for BragiError {
     }
 }
 
+impl From<failure::Error> for BragiError {
+    fn from(e: failure::Error) -> Self {
+        // the elasticsearch errors are kept as is to be able to
+        // tell when elasticsearch is unavailable
+        match e.downcast::<EsError>() {
+            Ok(es_error) => BragiError::Es(es_error),
+            Err(e) => BragiError::Store(e),
+        }
+    }
+}
+
+/// A published munin index, listed by `/status`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IndexStatus {
+    pub index: String,
+    pub doc_type: String,
+    pub dataset: Option<String>,
+    /// true if the index is searched by default (else the dataset must be requested)
+    pub public: bool,
+    pub nb_documents: u64,
+}
+
 #[derive(Serialize, Debug)]
 pub struct Geocoding {
     version: String,
     query: Option<String>,
+    /// the parts of the address recognized in the query (only with `_debug`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parsed_query: Option<mimir::store::ParsedQuery>,
 }
 
 #[derive(Serialize, Debug)]
@@ -177,7 +232,7 @@ impl FromWithLang<&mimir::Admin> for AssociatedAdmin {
 pub struct GeocodingResponse {
     pub id: String,
     #[serde(rename = "type")]
-    pub place_type: String, // FIXME: use an enum?
+    pub place_type: &'static str, // the `api_name` of the `PlaceKind`
     #[serde(skip_serializing_if = "Option::is_none")]
     pub zone_type: Option<String>,
     pub label: Option<String>,
@@ -213,6 +268,17 @@ pub struct GeocodingResponse {
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub lines: Vec<mimir::Line>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub color: Option<mimir::Rgb>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub text_color: Option<mimir::Rgb>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub network: Option<mimir::Network>,
+    /// the ids of the stop areas served by a line
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub stop_areas: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
     pub timezone: Option<String>,
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub codes: Vec<mimir::Code>,
@@ -240,6 +306,7 @@ impl ToGeom for mimir::Place {
             mimir::Place::Addr(ref addr) => addr.coord.to_geom(),
             mimir::Place::Poi(ref poi) => poi.coord.to_geom(),
             mimir::Place::Stop(ref stop) => stop.coord.to_geom(),
+            mimir::Place::Line(ref line) => line.coord.to_geom(),
         }
     }
 }
@@ -261,6 +328,7 @@ impl FromWithLang<mimir::Place> for Feature {
             mimir::Place::Addr(addr) => GeocodingResponse::from_with_lang(addr, lang),
             mimir::Place::Poi(poi) => GeocodingResponse::from_with_lang(poi, lang),
             mimir::Place::Stop(poi) => GeocodingResponse::from_with_lang(poi, lang),
+            mimir::Place::Line(line) => GeocodingResponse::from_with_lang(line, lang),
         };
         Feature {
             feature_type: "Feature".to_string(),
@@ -311,7 +379,7 @@ impl FromWithLang<mimir::Admin> for GeocodingResponse {
         GeocodingResponse {
             id: other.id,
             citycode: insee,
-            place_type: "zone".to_owned(),
+            place_type: PlaceKind::Admin.api_name(),
             level,
             zone_type: if zone_type.is_empty() {
                 None
@@ -346,7 +414,6 @@ fn get_citycode(admins: &[Arc<mimir::Admin>]) -> Option<String> {
 
 impl FromWithLang<mimir::Street> for GeocodingResponse {
     fn from_with_lang(other: mimir::Street, lang: Option<&str>) -> GeocodingResponse {
-        let type_ = "street".to_string();
         let name = Some(other.name);
         let label = Some(other.label);
         let admins = other.administrative_regions;
@@ -366,7 +433,7 @@ impl FromWithLang<mimir::Street> for GeocodingResponse {
         GeocodingResponse {
             id: other.id,
             citycode: citycode,
-            place_type: type_,
+            place_type: PlaceKind::Street.api_name(),
             name: name.clone(),
             postcode: postcode,
             label: label,
@@ -381,7 +448,6 @@ impl FromWithLang<mimir::Street> for GeocodingResponse {
 
 impl FromWithLang<mimir::Addr> for GeocodingResponse {
     fn from_with_lang(other: mimir::Addr, lang: Option<&str>) -> GeocodingResponse {
-        let type_ = "house".to_string();
         let label = Some(other.label);
         let housenumber = Some(other.house_number.to_string());
         let street_name = Some(other.street.name.to_string());
@@ -403,7 +469,7 @@ impl FromWithLang<mimir::Addr> for GeocodingResponse {
         GeocodingResponse {
             id: other.id,
             citycode: citycode,
-            place_type: type_,
+            place_type: PlaceKind::Addr.api_name(),
             name: name,
             postcode: postcode,
             label: label,
@@ -429,7 +495,6 @@ impl FromWithLang<mimir::Poi> for GeocodingResponse {
         };
         let name = Some(name.to_owned());
         let label = Some(label.to_owned());
-        let type_ = "poi".to_string();
         let admins = other.administrative_regions;
         let city = get_city_name(&admins);
         let postcode = if other.zip_codes.is_empty() {
@@ -447,7 +512,7 @@ impl FromWithLang<mimir::Poi> for GeocodingResponse {
         GeocodingResponse {
             id: other.id,
             citycode: citycode,
-            place_type: type_,
+            place_type: PlaceKind::Poi.api_name(),
             name: name,
             postcode: postcode,
             label: label,
@@ -472,7 +537,6 @@ impl FromWithLang<mimir::Poi> for GeocodingResponse {
 
 impl FromWithLang<mimir::Stop> for GeocodingResponse {
     fn from_with_lang(other: mimir::Stop, lang: Option<&str>) -> GeocodingResponse {
-        let type_ = "public_transport:stop_area".to_string();
         let label = Some(other.label);
         let name = Some(other.name);
         let admins = other.administrative_regions;
@@ -492,7 +556,7 @@ impl FromWithLang<mimir::Stop> for GeocodingResponse {
         GeocodingResponse {
             id: other.id,
             citycode: citycode,
-            place_type: type_,
+            place_type: PlaceKind::Stop.api_name(),
             name: name,
             postcode: postcode,
             label: label,
@@ -512,6 +576,26 @@ impl FromWithLang<mimir::Stop> for GeocodingResponse {
     }
 }
 
+impl FromWithLang<mimir::Line> for GeocodingResponse {
+    fn from_with_lang(other: mimir::Line, _lang: Option<&str>) -> GeocodingResponse {
+        GeocodingResponse {
+            id: other.id,
+            place_type: PlaceKind::Line.api_name(),
+            name: Some(other.name),
+            label: Some(other.label),
+            code: other.code,
+            color: other.color,
+            text_color: other.text_color,
+            network: other.network,
+            commercial_modes: other.commercial_mode.into_iter().collect(),
+            physical_modes: other.physical_modes,
+            stop_areas: other.stop_areas,
+            bbox: other.bbox,
+            ..Default::default()
+        }
+    }
+}
+
 #[derive(Serialize, Debug)]
 pub struct Autocomplete {
     #[serde(rename = "type")]
@@ -528,6 +612,7 @@ impl Autocomplete {
             geocoding: Geocoding {
                 version: "0.1.0".to_string(),
                 query: Some(q),
+                parsed_query: None,
             },
             features: features,
         }
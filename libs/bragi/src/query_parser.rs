@@ -0,0 +1,195 @@
+//! Recognition of the parts of an address in the query string of the autocomplete.
+//!
+//! The recognized parts are not removed from the query, they are only used
+//! to filter the places:
+//! - a house number at the beginning (`12 bis rue de la paix`) or at the end
+//!   (`rue de la paix 12`) of the query, with its suffix if any,
+//! - a postcode (5 digits, as in France),
+//! - a city, after the last comma (`rue de la paix, Melun`, but not `12, rue de la paix`)
+//!   or after the postcode (`rue de la paix 77000 Melun`).
+
+use mimir::store::ParsedQuery;
+
+/// the suffixes of the house numbers that can be separated from the number
+const SUFFIX_WORDS: &[&str] = &["bis", "ter", "quater", "quinquies"];
+
+fn is_postcode(word: &str) -> bool {
+    word.len() == 5 && word.chars().all(|c| c.is_ascii_digit())
+}
+
+fn is_suffix_word(word: &str) -> bool {
+    SUFFIX_WORDS.contains(&word.to_lowercase().as_str())
+}
+
+// a house number of at most `max_digits` digits, with its suffix if any
+// (a suffix word or a single letter: `12bis`, `12b`), as `12 bis` or `12 b`
+fn house_number(word: &str, max_digits: usize) -> Option<String> {
+    let nb_digits = word
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or_else(|| word.len());
+    if nb_digits == 0 || nb_digits > max_digits {
+        return None;
+    }
+    let (number, suffix) = word.split_at(nb_digits);
+    if suffix.is_empty() {
+        Some(number.to_string())
+    } else if is_suffix_word(suffix)
+        || (suffix.chars().count() == 1 && suffix.chars().all(char::is_alphabetic))
+    {
+        Some(format!("{} {}", number, suffix.to_lowercase()))
+    } else {
+        None
+    }
+}
+
+fn is_city(words: &str) -> bool {
+    !words.is_empty() && !words.chars().any(|c| c.is_ascii_digit())
+}
+
+// a house number alone (`12`, `12 bis`), without any street
+fn is_lone_house_number(words: &str) -> bool {
+    match words.split_whitespace().collect::<Vec<_>>().as_slice() {
+        [number] => house_number(number, 4).is_some(),
+        [number, suffix] => {
+            number.chars().all(|c| c.is_ascii_digit())
+                && house_number(number, 4).is_some()
+                && is_suffix_word(suffix)
+        }
+        _ => false,
+    }
+}
+
+/// recognize the house number, the postcode and the city in the query
+pub fn parse(q: &str) -> ParsedQuery {
+    let mut parsed = ParsedQuery::default();
+
+    // a city after the last comma, unless the comma follows the house number
+    // (`12, rue de la paix`)
+    let mut address = q;
+    if let Some(pos) = q.rfind(',') {
+        let city = q[pos + 1..].trim();
+        if is_city(city) && !is_lone_house_number(&q[..pos]) {
+            parsed.city = Some(city.to_string());
+            address = &q[..pos];
+        }
+    }
+    let mut words: Vec<&str> = address
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    // the last postcode, with the city after it (if there is a street before it)
+    if let Some(pos) = words.iter().rposition(|w| is_postcode(w)) {
+        parsed.postcode = Some(words[pos].to_string());
+        let city = words[pos + 1..].join(" ");
+        if pos > 0 && parsed.city.is_none() && is_city(&city) {
+            parsed.city = Some(city);
+        }
+        words.remove(pos);
+        if parsed.city.is_some() {
+            words.truncate(pos);
+        }
+    }
+
+    // the house number, before the street or after it, but never alone
+    if words.len() < 2 {
+        return parsed;
+    }
+    let nb_words = words.len();
+    let with_suffix_word = |number: String, suffix: &str| {
+        if !number.contains(' ') && is_suffix_word(suffix) {
+            format!("{} {}", number, suffix.to_lowercase())
+        } else {
+            number
+        }
+    };
+    parsed.house_number = house_number(words[0], 4)
+        .map(|number| with_suffix_word(number, words[1]))
+        // the numbers at the end of the street have at most 3 digits,
+        // not to take the years of the street names (`rue du 8 mai 1945`) for house numbers
+        .or_else(|| house_number(words[nb_words - 1], 3))
+        .or_else(|| {
+            if nb_words > 2 && is_suffix_word(words[nb_words - 1]) {
+                house_number(words[nb_words - 2], 3)
+                    .map(|number| with_suffix_word(number, words[nb_words - 1]))
+            } else {
+                None
+            }
+        });
+    parsed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(
+        house_number: Option<&str>,
+        postcode: Option<&str>,
+        city: Option<&str>,
+    ) -> ParsedQuery {
+        ParsedQuery {
+            house_number: house_number.map(str::to_string),
+            postcode: postcode.map(str::to_string),
+            city: city.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_house_number() {
+        assert_eq!(parse("12 rue de la paix"), parsed(Some("12"), None, None));
+        assert_eq!(
+            parse("12 bis rue de la paix"),
+            parsed(Some("12 bis"), None, None)
+        );
+        assert_eq!(
+            parse("12BIS rue de la paix"),
+            parsed(Some("12 bis"), None, None)
+        );
+        assert_eq!(
+            parse("12b rue de la paix"),
+            parsed(Some("12 b"), None, None)
+        );
+        assert_eq!(parse("rue de la paix 12"), parsed(Some("12"), None, None));
+        assert_eq!(
+            parse("rue de la paix 12 ter"),
+            parsed(Some("12 ter"), None, None)
+        );
+        // not a house number
+        assert_eq!(parse("rue du 8 mai 1945"), parsed(None, None, None));
+        assert_eq!(parse("12"), parsed(None, None, None));
+        assert_eq!(parse("rue de la paix"), ParsedQuery::default());
+    }
+
+    #[test]
+    fn test_postcode_and_city() {
+        assert_eq!(
+            parse("2 rue de la reine blanche 77000 Melun"),
+            parsed(Some("2"), Some("77000"), Some("Melun"))
+        );
+        assert_eq!(
+            parse("2 rue de la reine blanche, 77000 Melun"),
+            parsed(Some("2"), Some("77000"), Some("Melun"))
+        );
+        assert_eq!(
+            parse("rue de la reine blanche 2, Melun"),
+            parsed(Some("2"), None, Some("Melun"))
+        );
+        assert_eq!(parse("77000 Melun"), parsed(None, Some("77000"), None));
+        assert_eq!(
+            parse("rue de la reine blanche 77000"),
+            parsed(None, Some("77000"), None)
+        );
+        // the comma is not followed by a city
+        assert_eq!(parse("rue de la paix, 12"), parsed(Some("12"), None, None));
+        assert_eq!(
+            parse("2, Rue de la Reine Blanche"),
+            parsed(Some("2"), None, None)
+        );
+        assert_eq!(
+            parse("2 bis, Rue de la Reine Blanche, Melun"),
+            parsed(Some("2 bis"), None, Some("Melun"))
+        );
+        assert_eq!(parse(", Melun"), parsed(None, None, Some("Melun")));
+    }
+}
@@ -1,42 +1,14 @@
 use crate::extractors::BragiQuery;
 use crate::model::{Autocomplete, BragiError, FromWithLang};
-use crate::routes::params;
-use crate::{model, query, Context};
+use crate::routes::params::{self, Type};
+use crate::{model, query, query_parser, Context};
 use actix_web::web::{Data, Json};
 use geojson::{GeoJson, Geometry};
 use mimir::objects::Coord;
+use mimir::store::SearchQuery;
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
-#[derive(Copy, Clone, Debug, Deserialize, Serialize)]
-enum Type {
-    #[serde(rename = "city")]
-    City,
-    #[serde(rename = "house")]
-    House,
-    #[serde(rename = "poi")]
-    Poi,
-    #[serde(rename = "public_transport:stop_area")]
-    StopArea,
-    #[serde(rename = "street")]
-    Street,
-    #[serde(rename = "zone")]
-    Zone,
-}
-
-impl Type {
-    fn as_str(&self) -> &'static str {
-        match *self {
-            Type::City => "city",
-            Type::House => "house",
-            Type::Poi => "poi",
-            Type::StopArea => "public_transport:stop_area",
-            Type::Street => "street",
-            Type::Zone => "zone",
-        }
-    }
-}
-
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(untagged)]
 enum PoiType {
@@ -85,6 +57,10 @@ pub struct Params {
     // It is prefixed by an underscore to indicate its not a public parameter.
     #[serde(default, rename = "_debug")]
     debug: Option<bool>,
+    // Name of the ranking profile (the relevance constants) used to order the results.
+    // It is prefixed by an underscore to indicate its not a public parameter.
+    #[serde(rename = "_profile")]
+    profile: Option<String>,
 }
 
 impl Params {
@@ -139,33 +115,46 @@ pub fn call_autocomplete(
     shape: Option<Geometry>,
 ) -> Result<Json<Autocomplete>, model::BragiError> {
     let langs = params.langs();
-    let rubber = state.get_rubber_for_autocomplete(params.timeout());
-    let res = query::autocomplete(
-        &params.q,
-        &params
-            .pt_dataset
-            .iter()
-            .map(String::as_str)
-            .collect::<Vec<_>>(),
-        &params
-            .poi_dataset
-            .iter()
-            .map(String::as_str)
-            .collect::<Vec<_>>(),
-        params.all_data,
-        params.offset,
-        params.limit,
-        params.coord()?,
+    let mut store = state.get_store_for_autocomplete(params.timeout());
+    let pt_datasets = params
+        .pt_dataset
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    let poi_datasets = params
+        .poi_dataset
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    let types = params.types_as_str();
+    let zone_types = params.zone_types_as_str();
+    let poi_types = params.poi_types_as_str();
+    let profile = state.get_ranking_profile(params.profile.as_ref().map(String::as_str))?;
+    let parsed = query_parser::parse(&params.q);
+    let debug = params.debug.unwrap_or(false);
+    let query = SearchQuery {
+        q: &params.q,
+        pt_datasets: &pt_datasets,
+        poi_datasets: &poi_datasets,
+        all_data: params.all_data,
+        offset: params.offset,
+        limit: params.limit,
+        coord: params.coord()?,
         shape,
-        &params.types_as_str(),
-        &params.zone_types_as_str(),
-        &params.poi_types_as_str(),
-        &langs,
-        rubber,
-        params.debug.unwrap_or(false),
-    );
-    res.map(|r| Autocomplete::from_with_lang(r, langs.into_iter().next()))
-        .map(Json)
+        types: &types,
+        zone_types: &zone_types,
+        poi_types: &poi_types,
+        langs: &langs,
+        debug,
+        profile: Some(profile),
+        parsed: Some(&parsed),
+    };
+    let mut autocomplete = query::autocomplete(&query, &mut *store)
+        .map(|r| Autocomplete::from_with_lang(r, langs.first().cloned()))?;
+    if debug {
+        autocomplete.geocoding.parsed_query = Some(parsed);
+    }
+    Ok(Json(autocomplete))
 }
 
 pub fn autocomplete(
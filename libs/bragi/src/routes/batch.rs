@@ -0,0 +1,326 @@
+use crate::extractors::BragiQuery;
+use crate::model::{BragiError, Feature, FromWithLang};
+use crate::routes::params::{self, Type};
+use crate::{query, query_parser, Context};
+use actix_web::http::header::CONTENT_TYPE;
+use actix_web::web::{self, Bytes, Data};
+use actix_web::{HttpRequest, HttpResponse};
+use futures::{stream, Future, Stream};
+use mimir::embedded::{place_tokens, tokenize};
+use mimir::store::{PlaceSearch, SearchQuery, DEFAULT_REVERSE_RADIUS};
+use mimir::Place;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// maximum size (in bytes) of the body of a batch
+///
+/// The whole body is read (and its rows parsed) before the geocoding starts,
+/// so the batches are kept small: a bigger file must be split in several batches.
+pub const MAX_BATCH_SIZE: usize = 8 * 1024 * 1024;
+
+/// maximum number of rows of a batch
+pub const MAX_BATCH_ROWS: usize = 50_000;
+
+/// the values of a row of a batch, by column
+type Row = BTreeMap<String, String>;
+
+/// the place found for a row, with the confidence in this place
+type RowMatch = Result<Option<(Feature, f64)>, String>;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum MatchStatus {
+    /// a place has been found
+    Matched,
+    /// no place has been found
+    NotFound,
+    /// the row could not be geocoded
+    Error,
+}
+
+/// The result of a row, one json line of the response
+#[derive(Serialize, Debug)]
+struct RowResult {
+    /// position of the row in the batch (starting at 1, the header of a csv excluded)
+    row: usize,
+    status: MatchStatus,
+    /// confidence in the place, between 0 and 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    score: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    feature: Option<Feature>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl RowResult {
+    fn new(row: usize, result: RowMatch) -> RowResult {
+        let (status, score, feature, error) = match result {
+            Ok(Some((feature, score))) => (MatchStatus::Matched, Some(score), Some(feature), None),
+            Ok(None) => (MatchStatus::NotFound, None, None, None),
+            Err(e) => (MatchStatus::Error, None, None, Some(e)),
+        };
+        RowResult {
+            row,
+            status,
+            score,
+            feature,
+            error,
+        }
+    }
+
+    fn to_line(&self) -> Bytes {
+        let mut line = serde_json::to_vec(self)
+            .unwrap_or_else(|e| panic!("impossible to serialize the result of a row: {}", e));
+        line.push(b'\n');
+        Bytes::from(line)
+    }
+}
+
+/// read the rows of the batch: a csv with a header if the content type is `text/csv`,
+/// json lines (one object by row) otherwise
+///
+/// Only an unreadable batch (or a batch of more than `MAX_BATCH_ROWS` rows) is an error,
+/// an invalid row is reported in its result.
+fn read_rows(req: &HttpRequest, body: &[u8]) -> Result<Vec<Result<Row, String>>, BragiError> {
+    let is_csv = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|c| c.to_str().ok())
+        .map_or(false, |c| c.starts_with("text/csv"));
+    let rows = if is_csv {
+        read_csv_rows(body)?
+    } else {
+        read_json_rows(body)?
+    };
+    if rows.len() > MAX_BATCH_ROWS {
+        return Err(BragiError::InvalidBatch(format!(
+            "more than {} rows in the batch",
+            MAX_BATCH_ROWS
+        )));
+    }
+    Ok(rows)
+}
+
+fn read_csv_rows(body: &[u8]) -> Result<Vec<Result<Row, String>>, BragiError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(body);
+    let headers = reader
+        .headers()
+        .map_err(|e| BragiError::InvalidBatch(format!("invalid csv header: {}", e)))?
+        .clone();
+    Ok(reader
+        .records()
+        // the rows after the maximum are not read
+        .take(MAX_BATCH_ROWS + 1)
+        .map(|record| {
+            let record = record.map_err(|e| format!("invalid csv row: {}", e))?;
+            Ok(headers
+                .iter()
+                .zip(record.iter())
+                .map(|(column, value)| (column.to_string(), value.to_string()))
+                .collect())
+        })
+        .collect())
+}
+
+fn read_json_rows(body: &[u8]) -> Result<Vec<Result<Row, String>>, BragiError> {
+    let body = std::str::from_utf8(body)
+        .map_err(|e| BragiError::InvalidBatch(format!("invalid json lines: {}", e)))?;
+    Ok(body
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .take(MAX_BATCH_ROWS + 1)
+        .map(|line| {
+            let values: BTreeMap<String, serde_json::Value> =
+                serde_json::from_str(line).map_err(|e| format!("invalid json row: {}", e))?;
+            Ok(values
+                .into_iter()
+                .filter_map(|(column, value)| match value {
+                    serde_json::Value::String(s) => Some((column, s)),
+                    serde_json::Value::Null => None,
+                    value => Some((column, value.to_string())),
+                })
+                .collect())
+        })
+        .collect())
+}
+
+// geocode the rows on the blocking thread pool, at most `concurrency` at the same time,
+// and stream their results in the order of the rows
+fn stream_results<F>(rows: Vec<Result<Row, String>>, concurrency: usize, geocode: F) -> HttpResponse
+where
+    F: Fn(Row) -> RowMatch + Send + Sync + 'static,
+{
+    let geocode = Arc::new(geocode);
+    let results = stream::iter_ok::<_, actix_web::Error>(rows.into_iter().enumerate())
+        .map(move |(pos, row)| {
+            let geocode = geocode.clone();
+            web::block(move || -> Result<Bytes, ()> {
+                Ok(RowResult::new(pos + 1, row.and_then(geocode.as_ref())).to_line())
+            })
+            .map_err(actix_web::Error::from)
+        })
+        .buffered(concurrency);
+    HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .streaming(results)
+}
+
+fn default_columns() -> Vec<String> {
+    vec!["q".to_string()]
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SearchParams {
+    /// the columns whose values are joined to build the query of a row
+    #[serde(default = "default_columns", rename = "column")]
+    columns: Vec<String>,
+    #[serde(default, rename = "type")]
+    types: Vec<Type>,
+    #[serde(default)]
+    pt_dataset: Vec<String>,
+    #[serde(default)]
+    poi_dataset: Vec<String>,
+    lang: Option<String>,
+    /// timeout in milliseconds of the geocoding of a row
+    timeout: Option<u64>,
+}
+
+impl SearchParams {
+    fn query_of(&self, row: &Row) -> Result<String, String> {
+        let q = self
+            .columns
+            .iter()
+            .filter_map(|column| row.get(column))
+            .map(|value| value.trim())
+            .filter(|value| !value.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        if q.is_empty() {
+            Err(format!("no value in the columns {:?}", self.columns))
+        } else {
+            Ok(q)
+        }
+    }
+}
+
+/// share of the words of the query found in the place
+fn match_score(q: &str, place: &Place) -> f64 {
+    let words = tokenize(q);
+    if words.is_empty() {
+        return 0.;
+    }
+    let place_words = place_tokens(place);
+    let nb_found = words
+        .iter()
+        .filter(|w| place_words.binary_search(*w).is_ok())
+        .count();
+    nb_found as f64 / words.len() as f64
+}
+
+fn search_row(store: &mut dyn PlaceSearch, params: &SearchParams, row: &Row) -> RowMatch {
+    let q = params.query_of(row)?;
+    let pt_datasets: Vec<&str> = params.pt_dataset.iter().map(String::as_str).collect();
+    let poi_datasets: Vec<&str> = params.poi_dataset.iter().map(String::as_str).collect();
+    let types: Vec<&str> = params.types.iter().map(Type::as_str).collect();
+    let langs: Vec<&str> = params.lang.iter().map(String::as_str).collect();
+    let parsed = query_parser::parse(&q);
+    let query = SearchQuery {
+        q: &q,
+        pt_datasets: &pt_datasets,
+        poi_datasets: &poi_datasets,
+        types: &types,
+        langs: &langs,
+        limit: 1,
+        parsed: Some(&parsed),
+        ..Default::default()
+    };
+    let places = query::autocomplete(&query, store).map_err(|e| e.to_string())?;
+    Ok(places.into_iter().next().map(|place| {
+        let score = match_score(&q, &place);
+        (
+            Feature::from_with_lang(place, langs.first().cloned()),
+            score,
+        )
+    }))
+}
+
+/// geocode each row of the batch with the autocomplete,
+/// streaming one json line by row with its best place
+pub fn batch_search(
+    req: HttpRequest,
+    params: BragiQuery<SearchParams>,
+    state: Data<Context>,
+    body: Bytes,
+) -> Result<HttpResponse, BragiError> {
+    let rows = read_rows(&req, &body)?;
+    let params = (*params).clone();
+    let concurrency = state.batch_concurrency;
+    // the rubber is built once for the whole batch, its clones share its connections
+    let rubber = state.get_rubber_for_autocomplete(params.timeout.map(Duration::from_millis));
+    Ok(stream_results(rows, concurrency, move |row| {
+        let mut store = state.store_or(|| rubber.clone());
+        search_row(&mut *store, &params, &row)
+    }))
+}
+
+fn default_lat_column() -> String {
+    "lat".to_string()
+}
+
+fn default_lon_column() -> String {
+    "lon".to_string()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReverseParams {
+    #[serde(default = "default_lat_column")]
+    lat_column: String,
+    #[serde(default = "default_lon_column")]
+    lon_column: String,
+    /// timeout in milliseconds of the geocoding of a row
+    timeout: Option<u64>,
+}
+
+fn reverse_row(store: &mut dyn PlaceSearch, params: &ReverseParams, row: &Row) -> RowMatch {
+    let value = |column: &str| -> Result<f64, String> {
+        let value = row
+            .get(column)
+            .ok_or_else(|| format!("no column {}", column))?;
+        value
+            .parse()
+            .map_err(|_| format!("invalid {} {:?}", column, value))
+    };
+    let coord = params::make_coord(value(&params.lon_column)?, value(&params.lat_column)?)
+        .map_err(|e| e.to_string())?;
+    let places = store.get_address(&coord).map_err(|e| e.to_string())?;
+    Ok(places.into_iter().next().map(|place| {
+        // the nearer the address, the better
+        let distance = f64::from(place.distance().unwrap_or(0));
+        let score = (1. - distance / DEFAULT_REVERSE_RADIUS).max(0.);
+        (Feature::from_with_lang(place, None), score)
+    }))
+}
+
+/// find the nearest address (or street) of each row of the batch,
+/// streaming one json line by row
+pub fn batch_reverse(
+    req: HttpRequest,
+    params: BragiQuery<ReverseParams>,
+    state: Data<Context>,
+    body: Bytes,
+) -> Result<HttpResponse, BragiError> {
+    let rows = read_rows(&req, &body)?;
+    let params = (*params).clone();
+    let concurrency = state.batch_concurrency;
+    // the rubber is built once for the whole batch, its clones share its connections
+    let rubber = state.get_rubber_for_reverse(params.timeout.map(Duration::from_millis));
+    Ok(stream_results(rows, concurrency, move |row| {
+        let mut store = state.store_or(|| rubber.clone());
+        reverse_row(&mut *store, &params, &row)
+    }))
+}
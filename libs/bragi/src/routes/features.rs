@@ -21,7 +21,7 @@ pub fn features(
     state: Data<Context>,
     id: Path<String>,
 ) -> Result<Json<model::Autocomplete>, model::BragiError> {
-    let rubber = state.get_rubber_for_features(params.timeout.map(Duration::from_millis));
+    let mut store = state.get_store_for_features(params.timeout.map(Duration::from_millis));
     let features = query::features(
         &params
             .pt_dataset
@@ -35,7 +35,7 @@ pub fn features(
             .collect::<Vec<_>>(),
         params.all_data,
         &*id,
-        rubber,
+        &mut *store,
     );
     features
         .map(|r| model::Autocomplete::from_with_lang(r, None))
@@ -1,12 +1,18 @@
 mod autocomplete;
+mod batch;
 mod entry_point;
 mod features;
 mod params;
 mod reverse;
 mod status;
+mod structured;
+mod synonyms;
 
 pub use autocomplete::{autocomplete, post_autocomplete, JsonParams};
+pub use batch::{batch_reverse, batch_search, MAX_BATCH_SIZE};
 pub use entry_point::entry_point;
 pub use features::features;
-pub use reverse::reverse;
+pub use reverse::{reverse, reverse_zones};
 pub use status::status;
+pub use structured::search_structured;
+pub use synonyms::synonyms;
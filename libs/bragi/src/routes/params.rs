@@ -1,10 +1,56 @@
 use crate::model::BragiError;
 use mimir::objects::Coord;
+use serde::{Deserialize, Serialize};
+
+/// the types of places that can be searched
+#[derive(Copy, Clone, Debug, Deserialize, Serialize)]
+pub enum Type {
+    #[serde(rename = "city")]
+    City,
+    #[serde(rename = "house")]
+    House,
+    #[serde(rename = "poi")]
+    Poi,
+    #[serde(rename = "public_transport:stop_area")]
+    StopArea,
+    #[serde(rename = "public_transport:line")]
+    Line,
+    #[serde(rename = "street")]
+    Street,
+    #[serde(rename = "zone")]
+    Zone,
+}
+
+impl Type {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Type::City => "city",
+            Type::House => "house",
+            Type::Poi => "poi",
+            Type::StopArea => "public_transport:stop_area",
+            Type::Line => "public_transport:line",
+            Type::Street => "street",
+            Type::Zone => "zone",
+        }
+    }
+}
+
+/// maximum number of places returned by a search
+pub const MAX_LIMIT: u64 = 100;
+
+pub fn check_limit(limit: u64) -> Result<u64, BragiError> {
+    if limit > MAX_LIMIT {
+        Err(BragiError::InvalidParam("limit must be at most 100"))
+    } else {
+        Ok(limit)
+    }
+}
 
 pub fn make_coord(lon: f64, lat: f64) -> Result<Coord, BragiError> {
-    if lat < -90f64 || lat > 90f64 {
+    // the comparisons are false for NaN
+    if !lat.is_finite() || lat < -90f64 || lat > 90f64 {
         Err(BragiError::InvalidParam("lat is not a valid latitude"))
-    } else if lon < -180f64 || lon > 180f64 {
+    } else if !lon.is_finite() || lon < -180f64 || lon > 180f64 {
         Err(BragiError::InvalidParam("lon is not a valid longitude"))
     } else {
         Ok(Coord::new(lon, lat))
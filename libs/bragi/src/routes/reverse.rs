@@ -1,27 +1,95 @@
 use crate::extractors::BragiQuery;
-use crate::routes::params;
+use crate::routes::params::{self, Type};
 use crate::{model, model::FromWithLang, Context};
 use actix_web::web::{Data, Json};
+use mimir::objects::Place;
+use mimir::store::{ReverseQuery, ADDRESS_TYPES, DEFAULT_REVERSE_RADIUS};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
+fn default_radius() -> f64 {
+    DEFAULT_REVERSE_RADIUS
+}
+
+fn default_limit() -> u64 {
+    1u64
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Params {
     lat: f64,
     lon: f64,
+    /// maximum distance (in meters) of the places to the coord
+    #[serde(default = "default_radius")]
+    radius: f64,
+    #[serde(default = "default_limit")]
+    limit: u64,
+    /// the addresses and the streets if empty
+    #[serde(default, rename = "type")]
+    types: Vec<Type>,
+    #[serde(default)]
+    pt_dataset: Vec<String>,
+    #[serde(default)]
+    poi_dataset: Vec<String>,
     /// timeout in milliseconds
     timeout: Option<u64>,
 }
 
+impl Params {
+    fn types_as_str(&self) -> Vec<&str> {
+        if self.types.is_empty() {
+            ADDRESS_TYPES.to_vec()
+        } else {
+            self.types.iter().map(Type::as_str).collect()
+        }
+    }
+}
+
 pub fn reverse(
     params: BragiQuery<Params>,
     state: Data<Context>,
 ) -> Result<Json<model::Autocomplete>, model::BragiError> {
-    let mut rubber = state.get_rubber_for_reverse(params.timeout.map(Duration::from_millis));
+    if !params.radius.is_finite() || params.radius <= 0. {
+        return Err(model::BragiError::InvalidParam("radius must be positive"));
+    }
+    let limit = params::check_limit(params.limit)?;
     let coord = params::make_coord(params.lon, params.lat)?;
-    rubber
-        .get_address(&coord)
+    let mut store = state.get_store_for_reverse(params.timeout.map(Duration::from_millis));
+    let types = params.types_as_str();
+    let pt_datasets: Vec<&str> = params.pt_dataset.iter().map(|s| s.as_str()).collect();
+    let poi_datasets: Vec<&str> = params.poi_dataset.iter().map(|s| s.as_str()).collect();
+    let query = ReverseQuery {
+        coord,
+        radius: params.radius,
+        limit,
+        types: &types,
+        pt_datasets: &pt_datasets,
+        poi_datasets: &poi_datasets,
+    };
+    store
+        .reverse(&query)
         .map_err(model::BragiError::from)
         .map(|r| model::Autocomplete::from_with_lang(r, None))
         .map(Json)
 }
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ZonesParams {
+    lat: f64,
+    lon: f64,
+}
+
+/// the admins containing the point, from the smallest one to the country
+pub fn reverse_zones(
+    params: BragiQuery<ZonesParams>,
+    state: Data<Context>,
+) -> Result<Json<model::Autocomplete>, model::BragiError> {
+    let zones = state.get_zones().ok_or(model::BragiError::ZonesNotLoaded)?;
+    let coord = params::make_coord(params.lon, params.lat)?;
+    let admins = zones
+        .get(&coord.0)
+        .into_iter()
+        .map(|admin| Place::Admin((*admin).clone()))
+        .collect();
+    Ok(Json(model::Autocomplete::from_with_lang(admins, None)))
+}
@@ -1,18 +1,50 @@
+use crate::model::IndexStatus;
 use crate::Context;
-use actix_web::web::{Data, Json};
+use actix_web::web::Data;
+use actix_web::HttpResponse;
+use mimir::rubber::{hide_password, IndexMetadata};
 use serde::{Deserialize, Serialize};
+use slog_scope::warn;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Status {
     pub version: String,
     pub es: String,
     pub status: String,
+    /// health of the elasticsearch cluster (none with an embedded index)
+    pub es_health: Option<String>,
+    /// the published indexes
+    pub indexes: Vec<IndexStatus>,
+    /// the published datasets, with the description of their import
+    pub datasets: Vec<IndexMetadata>,
 }
 
-pub fn status(state: Data<Context>) -> Json<Status> {
-    Json(Status {
+/// the status of bragi and of its elasticsearch
+/// (a 503 is returned if elasticsearch is not reachable)
+pub fn status(state: Data<Context>) -> HttpResponse {
+    let mut status = Status {
         version: env!("CARGO_PKG_VERSION").to_string(),
-        es: state.cnx_string.clone(),
+        es: hide_password(&state.cnx_string),
         status: "good".to_string(),
-    })
+        es_health: None,
+        indexes: vec![],
+        datasets: vec![],
+    };
+    match state.get_es_health() {
+        Ok(es_health) => status.es_health = es_health,
+        Err(e) => {
+            warn!("elasticsearch is not reachable: {}", e);
+            status.status = "elasticsearch unreachable".to_string();
+            return HttpResponse::ServiceUnavailable().json(status);
+        }
+    }
+    status.indexes = state.get_indexes().unwrap_or_else(|e| {
+        warn!("impossible to get the indexes: {}", e);
+        vec![]
+    });
+    status.datasets = state.get_datasets().unwrap_or_else(|e| {
+        warn!("impossible to get the metadata of the indexes: {}", e);
+        vec![]
+    });
+    HttpResponse::Ok().json(status)
 }
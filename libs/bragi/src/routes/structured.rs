@@ -0,0 +1,63 @@
+use crate::extractors::BragiQuery;
+use crate::model::{Autocomplete, BragiError, FromWithLang};
+use crate::routes::params;
+use crate::{query, Context};
+use actix_web::web::{Data, Json};
+use mimir::store::StructuredQuery;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+fn default_limit() -> u64 {
+    10u64
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Params {
+    housenumber: Option<String>,
+    street: Option<String>,
+    postcode: Option<String>,
+    city: Option<String>,
+    /// name or code of the country
+    country: Option<String>,
+    #[serde(default = "default_limit")]
+    limit: u64,
+    #[serde(default)]
+    offset: u64,
+    /// timeout in milliseconds
+    timeout: Option<u64>,
+    lang: Option<String>,
+    // Name of the ranking profile (the relevance constants) used to order the results.
+    // It is prefixed by an underscore to indicate its not a public parameter.
+    #[serde(rename = "_profile")]
+    profile: Option<String>,
+}
+
+// an empty part is ignored
+fn part(value: &Option<String>) -> Option<&str> {
+    value.as_ref().map(|v| v.trim()).filter(|v| !v.is_empty())
+}
+
+/// search the places matching each part of an address
+pub fn search_structured(
+    params: BragiQuery<Params>,
+    state: Data<Context>,
+) -> Result<Json<Autocomplete>, BragiError> {
+    let langs: Vec<&str> = params.lang.iter().map(|l| l.as_str()).collect();
+    let limit = params::check_limit(params.limit)?;
+    let profile = state.get_ranking_profile(params.profile.as_ref().map(String::as_str))?;
+    let mut store = state.get_store_for_autocomplete(params.timeout.map(Duration::from_millis));
+    let query = StructuredQuery {
+        house_number: part(&params.housenumber),
+        street: part(&params.street),
+        postcode: part(&params.postcode),
+        city: part(&params.city),
+        country: part(&params.country),
+        offset: params.offset,
+        limit,
+        langs: &langs,
+        profile: Some(profile),
+    };
+    query::search_structured(&query, &mut *store)
+        .map(|r| Autocomplete::from_with_lang(r, langs.first().cloned()))
+        .map(Json)
+}
@@ -0,0 +1,10 @@
+use crate::{model, Context};
+use actix_web::web::{Data, Json};
+use std::collections::BTreeMap;
+
+/// the synonyms used by each index
+pub fn synonyms(
+    state: Data<Context>,
+) -> Result<Json<BTreeMap<String, Vec<String>>>, model::BragiError> {
+    Ok(Json(state.get_synonyms()?))
+}
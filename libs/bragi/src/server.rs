@@ -1,6 +1,7 @@
 use crate::extractors::ActixError;
 use crate::routes::{
-    autocomplete, entry_point, features, post_autocomplete, reverse, status, JsonParams,
+    autocomplete, batch_reverse, batch_search, entry_point, features, post_autocomplete, reverse,
+    reverse_zones, search_structured, status, synonyms, JsonParams, MAX_BATCH_SIZE,
 };
 use crate::{Args, Context};
 use actix_web::FromRequest;
@@ -26,11 +27,33 @@ pub fn configure_server(cfg: &mut web::ServiceConfig) {
                 cfg.error_handler(|err, _req| ActixError::InvalidJson(format!("{}", err)).into())
             })),
     )
+    .service(
+        web::resource("/search/structured")
+            .name("search_structured")
+            .route(web::get().to(search_structured)),
+    )
+    .service(
+        web::resource("/batch/search")
+            .name("batch_search")
+            .route(web::post().to(batch_search))
+            .data(web::PayloadConfig::new(MAX_BATCH_SIZE)),
+    )
+    .service(
+        web::resource("/batch/reverse")
+            .name("batch_reverse")
+            .route(web::post().to(batch_reverse))
+            .data(web::PayloadConfig::new(MAX_BATCH_SIZE)),
+    )
     .service(
         web::resource("/status")
             .name("status")
             .route(web::get().to(status)),
     )
+    .service(
+        web::resource("/synonyms")
+            .name("synonyms")
+            .route(web::get().to(synonyms)),
+    )
     .service(
         web::resource("/features/{id}")
             .name("features")
@@ -40,6 +63,11 @@ pub fn configure_server(cfg: &mut web::ServiceConfig) {
         web::resource("/reverse")
             .name("reverse")
             .route(web::get().to(reverse)),
+    )
+    .service(
+        web::resource("/reverse/zones")
+            .name("reverse_zones")
+            .route(web::get().to(reverse_zones)),
     );
 }
 
@@ -90,7 +90,7 @@ impl DockerWrapper {
     pub fn new() -> Result<DockerWrapper, Box<dyn Error>> {
         let mut wrapper = DockerWrapper { ip: "".to_string() };
         wrapper.setup()?;
-        let rubber = Rubber::new_with_timeout(&wrapper.host(), Duration::from_secs(10)); // use a long timeout
+        let mut rubber = Rubber::new_with_timeout(&wrapper.host(), Duration::from_secs(10)); // use a long timeout
         rubber.initialize_templates().unwrap();
         Ok(wrapper)
     }
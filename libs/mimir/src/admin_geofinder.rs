@@ -27,8 +27,8 @@
 // IRC #navitia on freenode
 // https://groups.google.com/d/forum/navitia
 // www.navitia.io
+use crate::objects::Admin;
 use gst::rtree::{RTree, Rect};
-use mimir::Admin;
 use std::collections::{BTreeMap, BTreeSet};
 use std::iter::FromIterator;
 use std::sync::Arc;
@@ -170,6 +170,14 @@ impl AdminGeoFinder {
     }
 }
 
+impl std::fmt::Debug for AdminGeoFinder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AdminGeoFinder")
+            .field("nb_admins", &self.admin_by_id.len())
+            .finish()
+    }
+}
+
 impl Default for AdminGeoFinder {
     fn default() -> Self {
         AdminGeoFinder {
@@ -224,7 +232,7 @@ mod tests {
         geo_types::Point(geo_types::Coordinate { x: x, y: y })
     }
 
-    fn make_admin(offset: f64, zt: Option<ZoneType>) -> ::mimir::Admin {
+    fn make_admin(offset: f64, zt: Option<ZoneType>) -> crate::objects::Admin {
         make_complex_admin(&format!("admin:offset:{}", offset,), offset, zt, 1., None)
     }
 
@@ -234,7 +242,7 @@ mod tests {
         zt: Option<ZoneType>,
         zone_size: f64,
         parent_offset: Option<&str>,
-    ) -> ::mimir::Admin {
+    ) -> crate::objects::Admin {
         // the boundary is a big octogon
         // the zone_size param is used to control the area of the zone
         let shape = geo_types::Polygon::new(
@@ -253,8 +261,8 @@ mod tests {
         );
         let boundary = geo_types::MultiPolygon(vec![shape]);
 
-        let coord = ::mimir::Coord::new(4.0 + offset, 4.0 + offset);
-        ::mimir::Admin {
+        let coord = crate::objects::Coord::new(4.0 + offset, 4.0 + offset);
+        crate::objects::Admin {
             id: id.into(),
             level: 8,
             name: "city".to_string(),
@@ -0,0 +1,1002 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+//! Embedded storage of the places, for small deployments without elasticsearch
+//!
+//! All the documents are kept in memory and searched with a simple inverted index
+//! on the normalized words of their labels, following the same prefix/fuzzy logic as
+//! the elasticsearch queries (see `crate::query`).
+//!
+//! The store is persisted in a directory: each published index is a json lines file
+//! named after its type/dataset index (eg. `munin_addr_fr.jsonl`) holding the same
+//! documents as the ones sent to elasticsearch, and `manifest.json` lists those files.
+
+use crate::objects::{Admin, Coord, MimirObject, Place, PlaceKind};
+use crate::ranking::Decay;
+use crate::rubber::{
+    get_date_index_name, get_indexes, get_main_type_and_dataset_index, make_place, IndexSettings,
+    IndexVisibility, TypedIndex,
+};
+use crate::store::{
+    MatchType, PlaceSearch, PlaceStore, ReverseQuery, SearchQuery, StructuredQuery,
+};
+use cosmogony::ZoneType;
+use failure::{format_err, Error, ResultExt};
+use geo::prelude::HaversineDistance;
+use serde::{Deserialize, Serialize};
+use slog::slog_info;
+use slog_scope::info;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::fs;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct PublishedIndex {
+    doc_type: String,
+    dataset: String,
+    public: bool,
+    is_geo_data: bool,
+}
+
+impl PublishedIndex {
+    /// all the names under which the documents of the index can be searched,
+    /// the same ones as the elasticsearch aliases
+    fn aliases(&self, name: &str) -> Vec<String> {
+        let mut aliases = vec![name.to_string()];
+        if self.public {
+            aliases.push(format!("munin_{}", self.doc_type));
+            if self.is_geo_data {
+                aliases.push("munin_geo_data".to_string());
+            }
+            aliases.push("munin".to_string());
+        }
+        aliases
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Manifest {
+    indexes: BTreeMap<String, PublishedIndex>,
+}
+
+struct Document {
+    place: Place,
+    // position of the index of the document in `EmbeddedStore::indexes`
+    index: usize,
+    tokens: Vec<String>,
+}
+
+/// In-process `PlaceStore` persisted to a directory
+pub struct EmbeddedStore {
+    dir: PathBuf,
+    manifest: Manifest,
+    // name, doc_type and aliases of the published indexes
+    indexes: Vec<(String, String, Vec<String>)>,
+    documents: Vec<Document>,
+    // for each normalized word, the documents containing it
+    words: BTreeMap<String, Vec<usize>>,
+    ids: HashMap<String, Vec<usize>>,
+}
+
+impl fmt::Debug for EmbeddedStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EmbeddedStore")
+            .field("dir", &self.dir)
+            .field("nb_documents", &self.documents.len())
+            .finish()
+    }
+}
+
+/// lowercase the string and remove the accents of the latin letters
+fn normalize(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    for c in s.chars().flat_map(char::to_lowercase) {
+        match c {
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => res.push('a'),
+            'ç' => res.push('c'),
+            'è' | 'é' | 'ê' | 'ë' => res.push('e'),
+            'ì' | 'í' | 'î' | 'ï' => res.push('i'),
+            'ñ' => res.push('n'),
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' => res.push('o'),
+            'ù' | 'ú' | 'û' | 'ü' => res.push('u'),
+            'ý' | 'ÿ' => res.push('y'),
+            'æ' => res.push_str("ae"),
+            'œ' => res.push_str("oe"),
+            'ß' => res.push_str("ss"),
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+/// the normalized words of the string, like the `word` analyzer of elasticsearch
+pub fn tokenize(s: &str) -> Vec<String> {
+    normalize(s)
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + if ca == *cb { 0 } else { 1 };
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// number of misspelt letters accepted for a word in a fuzzy search
+fn max_typos(word: &str) -> usize {
+    match word.chars().count() {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+/// number of words of the query that must match in a fuzzy search,
+/// same rules as the `minimum_should_match` of the elasticsearch fuzzy query
+fn minimum_should_match(nb_words: usize) -> usize {
+    match nb_words {
+        0..=1 => nb_words,
+        2..=3 => nb_words - 1,
+        4..=9 => nb_words - 2,
+        10..=20 => nb_words - 4,
+        _ => nb_words / 4,
+    }
+}
+
+fn index_matches(alias: &str, index: &str) -> bool {
+    if index.ends_with('*') {
+        alias.starts_with(&index[..index.len() - 1])
+    } else {
+        alias == index
+    }
+}
+
+fn place_id(place: &Place) -> &str {
+    match place {
+        Place::Admin(ref o) => &o.id,
+        Place::Street(ref o) => &o.id,
+        Place::Addr(ref o) => &o.id,
+        Place::Poi(ref o) => &o.id,
+        Place::Stop(ref o) => &o.id,
+        Place::Line(ref o) => &o.id,
+    }
+}
+
+fn place_weight(place: &Place) -> f64 {
+    match place {
+        Place::Admin(ref o) => o.weight,
+        Place::Street(ref o) => o.weight,
+        Place::Addr(ref o) => o.weight,
+        Place::Poi(ref o) => o.weight,
+        Place::Stop(ref o) => o.weight,
+        Place::Line(ref o) => o.weight,
+    }
+}
+
+fn place_zip_codes(place: &Place) -> &[String] {
+    match place {
+        Place::Admin(ref o) => &o.zip_codes,
+        Place::Street(ref o) => &o.zip_codes,
+        Place::Addr(ref o) => &o.zip_codes,
+        Place::Poi(ref o) => &o.zip_codes,
+        Place::Stop(ref o) => &o.zip_codes,
+        Place::Line(_) => &[],
+    }
+}
+
+// the public transport datasets of the place (empty for the non-PT places)
+fn place_coverages(place: &Place) -> &[String] {
+    match place {
+        Place::Stop(ref o) => &o.coverages,
+        Place::Line(ref o) => &o.coverages,
+        _ => &[],
+    }
+}
+
+// the document as it has been stored in the index
+fn place_value(place: &Place) -> serde_json::Result<serde_json::Value> {
+    match place {
+        Place::Admin(ref o) => serde_json::to_value(o),
+        Place::Street(ref o) => serde_json::to_value(o),
+        Place::Addr(ref o) => serde_json::to_value(o),
+        Place::Poi(ref o) => serde_json::to_value(o),
+        Place::Stop(ref o) => serde_json::to_value(o),
+        Place::Line(ref o) => serde_json::to_value(o),
+    }
+}
+
+/// the words of the label, the name and the zip codes, like the `full_label` field in elasticsearch
+pub fn place_tokens(place: &Place) -> Vec<String> {
+    let mut tokens: Vec<String> = tokenize(place.label());
+    tokens.extend(place_zip_codes(place).iter().flat_map(|z| tokenize(z)));
+    tokens.sort();
+    tokens.dedup();
+    tokens
+}
+
+// the admins of the place, like the `administrative_regions.name` field in elasticsearch
+fn place_admins(place: &Place) -> &[Arc<Admin>] {
+    match place {
+        Place::Admin(ref o) => &o.administrative_regions,
+        Place::Street(ref o) => &o.administrative_regions,
+        Place::Addr(ref o) => &o.street.administrative_regions,
+        _ => &[],
+    }
+}
+
+fn place_country_codes(place: &Place) -> &[String] {
+    match place {
+        Place::Admin(ref o) => &o.country_codes,
+        Place::Street(ref o) => &o.country_codes,
+        Place::Addr(ref o) => &o.country_codes,
+        _ => &[],
+    }
+}
+
+// all the words of the value are in the texts,
+// like an elasticsearch match query with the `and` operator
+fn matches_all_words<'a>(value: &str, texts: impl IntoIterator<Item = &'a str>) -> bool {
+    let words: BTreeSet<String> = texts.into_iter().flat_map(tokenize).collect();
+    let value = tokenize(value);
+    !value.is_empty() && value.iter().all(|w| words.contains(w))
+}
+
+// the house number (`12 bis` or `12bis`) must be one word of the query,
+// or a few consecutive ones (`12 bis` and `12bis` being the same forms for elasticsearch)
+fn matches_house_number(house_number: &str, words: &[String]) -> bool {
+    let tokens = tokenize(house_number);
+    let joined = tokens.concat();
+    !joined.is_empty()
+        && (1..=tokens.len().max(2).min(words.len()))
+            .any(|size| words.windows(size).any(|w| w.concat() == joined))
+}
+
+// same decay function as the proximity boost of the elasticsearch query
+// (by default, exponential decay of 0.4 at 130km, starting after 20km)
+fn proximity(coord: &Coord, place: &Place, decay: &Decay) -> f64 {
+    let distance = geo::Point::from(coord.0).haversine_distance(&place.coord().0.into());
+    decay.proximity(distance)
+}
+
+fn is_in_shape(shape: &geojson::Geometry, coord: &Coord) -> bool {
+    use geo::algorithm::contains::Contains;
+    use std::convert::TryInto;
+    let point = geo::Point::from(coord.0);
+    let geometry: Result<geo::Geometry<f64>, _> = shape.value.clone().try_into();
+    match geometry {
+        Ok(geo::Geometry::Polygon(p)) => p.contains(&point),
+        Ok(geo::Geometry::MultiPolygon(p)) => p.contains(&point),
+        _ => false,
+    }
+}
+
+fn write_manifest(dir: &Path, manifest: &Manifest) -> Result<(), Error> {
+    let tmp_path = dir.join(format!("{}.tmp", MANIFEST_FILE));
+    let file = fs::File::create(&tmp_path)
+        .with_context(|_| format!("impossible to create {:?}", tmp_path))?;
+    serde_json::to_writer_pretty(BufWriter::new(file), manifest)?;
+    fs::rename(&tmp_path, dir.join(MANIFEST_FILE))?;
+    Ok(())
+}
+
+impl EmbeddedStore {
+    /// open the store persisted in the directory, creating it if needed
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<EmbeddedStore, Error> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)
+            .with_context(|_| format!("impossible to create directory {:?}", dir))?;
+        let manifest_path = dir.join(MANIFEST_FILE);
+        let manifest = if manifest_path.exists() {
+            let file = fs::File::open(&manifest_path)?;
+            serde_json::from_reader(BufReader::new(file))
+                .with_context(|_| format!("invalid manifest {:?}", manifest_path))?
+        } else {
+            Manifest::default()
+        };
+        let mut store = EmbeddedStore {
+            dir,
+            manifest,
+            indexes: vec![],
+            documents: vec![],
+            words: BTreeMap::new(),
+            ids: HashMap::new(),
+        };
+        store.load()?;
+        Ok(store)
+    }
+
+    /// number of documents in the published indexes
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    fn index_path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.jsonl", name))
+    }
+
+    fn tmp_index_path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.jsonl.tmp", name))
+    }
+
+    // read all the published indexes and build the search structures
+    fn load(&mut self) -> Result<(), Error> {
+        self.indexes.clear();
+        self.documents.clear();
+        self.words.clear();
+        self.ids.clear();
+        for (name, published) in &self.manifest.indexes {
+            let index = self.indexes.len();
+            self.indexes.push((
+                name.clone(),
+                published.doc_type.clone(),
+                published.aliases(name),
+            ));
+            let path = self.index_path(name);
+            let file =
+                fs::File::open(&path).with_context(|_| format!("impossible to read {:?}", path))?;
+            for line in BufReader::new(file).lines() {
+                let value: serde_json::Value = serde_json::from_str(&line?)?;
+                if let Some(place) =
+                    make_place(published.doc_type.clone(), Some(Box::new(value)), None)
+                {
+                    let tokens = place_tokens(&place);
+                    self.documents.push(Document {
+                        place,
+                        index,
+                        tokens,
+                    });
+                }
+            }
+        }
+        for (pos, doc) in self.documents.iter().enumerate() {
+            for token in &doc.tokens {
+                self.words.entry(token.clone()).or_default().push(pos);
+            }
+            self.ids
+                .entry(place_id(&doc.place).to_string())
+                .or_default()
+                .push(pos);
+        }
+        info!(
+            "embedded index {:?} loaded: {} documents in {} indexes",
+            self.dir,
+            self.documents.len(),
+            self.indexes.len()
+        );
+        Ok(())
+    }
+
+    // for each published index, tell if it is one of the requested indexes
+    fn selected_indexes(&self, requested: &[String]) -> Vec<bool> {
+        self.indexes
+            .iter()
+            .map(|(_, _, aliases)| {
+                aliases
+                    .iter()
+                    .any(|a| requested.iter().any(|r| index_matches(a, r)))
+            })
+            .collect()
+    }
+
+    fn documents_matching_word(&self, word: &str, match_type: MatchType) -> BTreeSet<usize> {
+        let mut docs: BTreeSet<usize> = self
+            .words
+            .range(word.to_string()..)
+            .take_while(|(w, _)| w.starts_with(word))
+            .flat_map(|(_, docs)| docs.iter().cloned())
+            .collect();
+        if match_type == MatchType::Fuzzy {
+            let max_typos = max_typos(word);
+            if max_typos > 0 {
+                docs.extend(
+                    self.words
+                        .iter()
+                        .filter(|(w, _)| levenshtein(word, w) <= max_typos)
+                        .flat_map(|(_, docs)| docs.iter().cloned()),
+                );
+            }
+        }
+        docs
+    }
+
+    // filters of the elasticsearch query that do not depend on the query string
+    fn is_accepted(&self, doc: &Document, query: &SearchQuery<'_>, words: &[String]) -> bool {
+        // the coverage of the stops and the lines must be one of the requested ones
+        let coverages = place_coverages(&doc.place);
+        if !query.all_data
+            && !coverages.is_empty()
+            && !coverages
+                .iter()
+                .any(|c| query.pt_datasets.contains(&c.as_str()))
+        {
+            return false;
+        }
+        let parsed = query.parsed;
+        // the house number must be the one recognized in the query, or else in the query
+        if let Place::Addr(ref addr) = doc.place {
+            let matches = match parsed.filter(|p| p.house_number.is_some()) {
+                Some(parsed) => parsed
+                    .house_number_forms()
+                    .iter()
+                    .any(|number| matches_all_words(number, Some(addr.house_number.as_str()))),
+                None => matches_house_number(&addr.house_number, words),
+            };
+            if !matches {
+                return false;
+            }
+        }
+        // the postcode recognized in the query must be one of the zip codes (if any)
+        if let Some(postcode) = parsed.and_then(|p| p.postcode.as_ref()) {
+            let zip_codes = place_zip_codes(&doc.place);
+            if !zip_codes.is_empty()
+                && !matches_all_words(postcode, zip_codes.iter().map(String::as_str))
+            {
+                return false;
+            }
+        }
+        // the city recognized in the query must be one of the admins, or the admin itself
+        if let Some(city) = parsed.and_then(|p| p.city.as_ref()) {
+            let admins = place_admins(&doc.place);
+            let matches = match doc.place {
+                Place::Admin(ref admin) => matches_all_words(city, Some(admin.name.as_str())),
+                _ => admins.is_empty(),
+            } || matches_all_words(city, admins.iter().map(|a| a.name.as_str()));
+            if !matches {
+                return false;
+            }
+        }
+        // the stops are not limited to the shape
+        if let Some(ref shape) = query.shape {
+            if !doc.place.is_stop() && !is_in_shape(shape, doc.place.coord()) {
+                return false;
+            }
+        }
+        if !query.zone_types.is_empty() {
+            let zone_type = match doc.place {
+                Place::Admin(ref admin) => admin
+                    .zone_type
+                    .as_ref()
+                    .and_then(|z| serde_json::to_value(z).ok())
+                    .and_then(|z| z.as_str().map(str::to_string)),
+                _ => None,
+            };
+            match zone_type {
+                Some(ref z) if query.zone_types.contains(&z.as_str()) => (),
+                _ => return false,
+            }
+        }
+        if !query.poi_types.is_empty() {
+            match doc.place {
+                Place::Poi(ref poi) if query.poi_types.contains(&poi.poi_type.id.as_str()) => (),
+                _ => return false,
+            }
+        }
+        true
+    }
+
+    // rough equivalent of the elasticsearch scoring:
+    // priority by type, then matching words, then importance (weight and proximity)
+    fn score(
+        &self,
+        doc: &Document,
+        nb_matching_words: usize,
+        query: &SearchQuery<'_>,
+        match_type: MatchType,
+    ) -> f64 {
+        let profile = query.profile();
+        let type_score =
+            profile.types.get(doc.place.kind()) / profile.types.max().max(std::f64::EPSILON);
+        let string_score = nb_matching_words as f64 / doc.tokens.len().max(1) as f64;
+        let weight = place_weight(&doc.place);
+        let factors = &profile.importance;
+        let (weight_factor, admin_weight) = match (query.coord.is_some(), match_type) {
+            (true, MatchType::Prefix) => (
+                factors.weight_factor_with_coord,
+                factors.admin_weight_with_coord,
+            ),
+            _ => (factors.weight_factor, factors.admin_weight),
+        };
+        let mut importance = weight * weight_factor;
+        if let Some(ref coord) = query.coord {
+            importance += factors.proximity_weight * proximity(coord, &doc.place, &profile.decay);
+        }
+        if match_type == MatchType::Prefix && doc.place.is_admin() {
+            importance += (weight * 1e6).ln_1p() * admin_weight;
+        }
+        type_score + string_score + importance
+    }
+
+    fn to_result(&self, doc: &Document, coord: Option<&Coord>) -> Place {
+        let mut place = doc.place.clone();
+        // like in elasticsearch, the (large) boundaries are not returned
+        if let Place::Admin(ref mut admin) = place {
+            admin.boundary = None;
+        }
+        if let Some(coord) = coord {
+            let distance =
+                geo::Point::from(coord.0).haversine_distance(&place.coord().0.into()) as u32;
+            place.set_distance(distance);
+        }
+        place
+    }
+
+    /// search the places matching the query
+    pub fn find_places(
+        &self,
+        query: &SearchQuery<'_>,
+        match_type: MatchType,
+    ) -> Result<Vec<Place>, Error> {
+        let indexes = get_indexes(
+            query.all_data,
+            query.pt_datasets,
+            query.poi_datasets,
+            query.types,
+        )?;
+        let selected = self.selected_indexes(&indexes);
+        let words = tokenize(query.q);
+        if words.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut nb_matching_words: HashMap<usize, usize> = HashMap::new();
+        for word in &words {
+            for doc in self.documents_matching_word(word, match_type) {
+                *nb_matching_words.entry(doc).or_insert(0) += 1;
+            }
+        }
+        let min_matching_words = match match_type {
+            MatchType::Prefix => words.len(),
+            MatchType::Fuzzy => minimum_should_match(words.len()),
+        };
+
+        let mut results: Vec<(f64, &Document)> = nb_matching_words
+            .into_iter()
+            .filter(|&(_, nb)| nb >= min_matching_words)
+            .map(|(pos, nb)| (&self.documents[pos], nb))
+            .filter(|(doc, _)| selected[doc.index] && self.is_accepted(doc, query, &words))
+            .map(|(doc, nb)| (self.score(doc, nb, query, match_type), doc))
+            .collect();
+        results.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        Ok(results
+            .into_iter()
+            .skip(query.offset as usize)
+            .take(query.limit as usize)
+            .map(|(_, doc)| self.to_result(doc, query.coord.as_ref()))
+            .collect())
+    }
+
+    /// get the places next to the coord of the query, the nearest first
+    pub fn find_reverse(&self, reverse: &ReverseQuery<'_>) -> Result<Vec<Place>, Error> {
+        let indexes = get_indexes(
+            false,
+            reverse.pt_datasets,
+            reverse.poi_datasets,
+            reverse.types,
+        )?;
+        let selected = self.selected_indexes(&indexes);
+        let query = SearchQuery {
+            pt_datasets: reverse.pt_datasets,
+            poi_datasets: reverse.poi_datasets,
+            ..Default::default()
+        };
+        let point = geo::Point::from(reverse.coord.0);
+        let mut results: Vec<(f64, &Document)> = self
+            .documents
+            .iter()
+            .filter(|doc| selected[doc.index])
+            .filter(|doc| match doc.place {
+                // only the coverage filter applies
+                Place::Stop(_) | Place::Line(_) => self.is_accepted(doc, &query, &[]),
+                _ => true,
+            })
+            .map(|doc| {
+                let distance = point.haversine_distance(&doc.place.coord().0.into());
+                (distance, doc)
+            })
+            .filter(|(distance, _)| *distance <= reverse.radius)
+            .collect();
+        results.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        Ok(results
+            .into_iter()
+            .take(reverse.limit as usize)
+            .map(|(_, doc)| self.to_result(doc, Some(&reverse.coord)))
+            .collect())
+    }
+
+    // same conditions as the elasticsearch structured query
+    fn matches_structured(&self, doc: &Document, structured: &StructuredQuery<'_>) -> bool {
+        let place = &doc.place;
+        let kind = structured.kind();
+        if place.kind() != kind {
+            return false;
+        }
+        let is_city = structured.city.is_some() || structured.postcode.is_some();
+        let admin_names = || place_admins(place).iter().map(|a| a.name.as_str());
+        let own_names = || -> Vec<&str> {
+            match place {
+                Place::Admin(ref admin) => std::iter::once(admin.name.as_str())
+                    .chain(structured.langs.iter().filter_map(|l| admin.names.get(l)))
+                    .collect(),
+                _ => vec![],
+            }
+        };
+        if let Some(house_number) = structured.house_number {
+            let number = match place {
+                Place::Addr(ref addr) => &addr.house_number,
+                _ => return false,
+            };
+            if !matches_all_words(house_number, Some(number.as_str())) {
+                return false;
+            }
+        }
+        if let Some(street) = structured.street {
+            let name = match place {
+                Place::Addr(ref addr) => &addr.street.name,
+                Place::Street(ref street) => &street.name,
+                _ => return false,
+            };
+            if !matches_all_words(street, Some(name.as_str())) {
+                return false;
+            }
+        }
+        if let Some(postcode) = structured.postcode {
+            if !matches_all_words(postcode, place_zip_codes(place).iter().map(String::as_str)) {
+                return false;
+            }
+        }
+        if let Some(city) = structured.city {
+            let matches = match kind {
+                PlaceKind::Admin => matches_all_words(city, own_names()),
+                _ => matches_all_words(city, admin_names()),
+            };
+            if !matches {
+                return false;
+            }
+        }
+        if let Some(country) = structured.country {
+            let matches_name = if kind == PlaceKind::Admin && !is_city {
+                matches_all_words(country, own_names())
+            } else {
+                matches_all_words(country, admin_names())
+            };
+            let country_codes = place_country_codes(place).iter().map(String::as_str);
+            if !matches_name && !matches_all_words(country, country_codes) {
+                return false;
+            }
+        }
+        if let Place::Admin(ref admin) = place {
+            let zone_type = if is_city {
+                ZoneType::City
+            } else {
+                ZoneType::Country
+            };
+            if admin.zone_type != Some(zone_type) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// search the places matching each part of the structured query, the most important first
+    pub fn find_structured(&self, structured: &StructuredQuery<'_>) -> Vec<Place> {
+        let selected = self.selected_indexes(&[structured.kind().index_prefix().to_string()]);
+        let mut results: Vec<&Document> = self
+            .documents
+            .iter()
+            .filter(|doc| selected[doc.index] && self.matches_structured(doc, structured))
+            .collect();
+        results.sort_by(|a, b| {
+            place_weight(&b.place)
+                .partial_cmp(&place_weight(&a.place))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        results
+            .into_iter()
+            .skip(structured.offset as usize)
+            .take(structured.limit as usize)
+            .map(|doc| self.to_result(doc, None))
+            .collect()
+    }
+
+    /// get the places with the given id
+    pub fn find_features(
+        &self,
+        pt_datasets: &[&str],
+        poi_datasets: &[&str],
+        all_data: bool,
+        id: &str,
+    ) -> Result<Vec<Place>, Error> {
+        let indexes = get_indexes(all_data, pt_datasets, poi_datasets, &[])?;
+        let selected = self.selected_indexes(&indexes);
+        let query = SearchQuery {
+            pt_datasets,
+            poi_datasets,
+            all_data,
+            ..Default::default()
+        };
+        Ok(self
+            .ids
+            .get(id)
+            .into_iter()
+            .flat_map(|docs| docs.iter())
+            .map(|pos| &self.documents[*pos])
+            .filter(|doc| selected[doc.index])
+            .filter(|doc| match doc.place {
+                // only the coverage filter applies
+                Place::Stop(_) | Place::Line(_) => self.is_accepted(doc, &query, &[]),
+                _ => true,
+            })
+            .map(|doc| self.to_result(doc, None))
+            .collect())
+    }
+}
+
+impl<'a> PlaceSearch for &'a EmbeddedStore {
+    fn reverse(&mut self, query: &ReverseQuery<'_>) -> Result<Vec<Place>, Error> {
+        self.find_reverse(query)
+    }
+
+    fn search(
+        &mut self,
+        query: &SearchQuery<'_>,
+        match_type: MatchType,
+    ) -> Result<Vec<Place>, Error> {
+        self.find_places(query, match_type)
+    }
+
+    fn search_structured(&mut self, query: &StructuredQuery<'_>) -> Result<Vec<Place>, Error> {
+        Ok(self.find_structured(query))
+    }
+
+    fn features(
+        &mut self,
+        pt_datasets: &[&str],
+        poi_datasets: &[&str],
+        all_data: bool,
+        id: &str,
+    ) -> Result<Vec<Place>, Error> {
+        self.find_features(pt_datasets, poi_datasets, all_data, id)
+    }
+}
+
+// the search only needs a shared access to the store, it is implemented on `&EmbeddedStore`
+impl PlaceSearch for EmbeddedStore {
+    fn reverse(&mut self, query: &ReverseQuery<'_>) -> Result<Vec<Place>, Error> {
+        (&*self).reverse(query)
+    }
+
+    fn search(
+        &mut self,
+        query: &SearchQuery<'_>,
+        match_type: MatchType,
+    ) -> Result<Vec<Place>, Error> {
+        (&*self).search(query, match_type)
+    }
+
+    fn search_structured(&mut self, query: &StructuredQuery<'_>) -> Result<Vec<Place>, Error> {
+        (&*self).search_structured(query)
+    }
+
+    fn features(
+        &mut self,
+        pt_datasets: &[&str],
+        poi_datasets: &[&str],
+        all_data: bool,
+        id: &str,
+    ) -> Result<Vec<Place>, Error> {
+        (&*self).features(pt_datasets, poi_datasets, all_data, id)
+    }
+}
+
+impl PlaceStore for EmbeddedStore {
+    /// the documents are written in a temporary file until the index is published.
+    /// The index settings are meaningless for this store.
+    fn make_index<T: MimirObject>(
+        &mut self,
+        dataset: &str,
+        _index_settings: &IndexSettings,
+    ) -> Result<TypedIndex<T>, Error> {
+        let index_name = get_date_index_name(&get_main_type_and_dataset_index::<T>(dataset));
+        info!("creating embedded index {}", index_name);
+        let path = self.tmp_index_path(&index_name);
+        fs::File::create(&path).with_context(|_| format!("impossible to create {:?}", path))?;
+        Ok(TypedIndex::new(index_name))
+    }
+
+    fn publish_index<T: MimirObject>(
+        &mut self,
+        dataset: &str,
+        index: TypedIndex<T>,
+        visibility: IndexVisibility,
+        _index_settings: &IndexSettings,
+    ) -> Result<(), Error> {
+        let dataset_index = get_main_type_and_dataset_index::<T>(dataset);
+        info!(
+            "publishing embedded index {} as {}",
+            index.name(),
+            dataset_index
+        );
+        fs::rename(
+            self.tmp_index_path(index.name()),
+            self.index_path(&dataset_index),
+        )
+        .with_context(|_| format!("impossible to publish index {}", index.name()))?;
+        self.manifest.indexes.insert(
+            dataset_index,
+            PublishedIndex {
+                doc_type: T::doc_type().to_string(),
+                dataset: dataset.to_string(),
+                public: match visibility {
+                    IndexVisibility::Public => true,
+                    IndexVisibility::Private => false,
+                },
+                is_geo_data: T::is_geo_data(),
+            },
+        );
+        write_manifest(&self.dir, &self.manifest)?;
+        self.load()
+    }
+
+    fn bulk_index<T, I>(&mut self, index: &TypedIndex<T>, iter: I) -> Result<usize, Error>
+    where
+        T: MimirObject + Send + 'static,
+        I: Iterator<Item = T>,
+    {
+        let path = self.tmp_index_path(index.name());
+        let file = fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .with_context(|_| format!("no index {}", index.name()))?;
+        let mut writer = BufWriter::new(file);
+        let mut nb = 0;
+        for elt in iter {
+            serde_json::to_writer(&mut writer, &elt)?;
+            writer.write_all(b"\n")?;
+            nb += 1;
+        }
+        writer.flush()?;
+        Ok(nb)
+    }
+
+    fn get_all_objects_from_index<T>(&mut self, index: &str) -> Result<Vec<T>, Error>
+    where
+        for<'de> T: MimirObject + serde::de::Deserialize<'de> + std::fmt::Debug,
+    {
+        let selected = self.selected_indexes(&[index.to_string()]);
+        self.documents
+            .iter()
+            .filter(|doc| selected[doc.index] && self.indexes[doc.index].1 == T::doc_type())
+            .map(|doc| {
+                let value = place_value(&doc.place)?;
+                serde_json::from_value(value).map_err(|e| {
+                    format_err!(
+                        "impossible to read {} from {}: {}",
+                        place_id(&doc.place),
+                        index,
+                        e
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokenize("20 Rue de l'Église (Châtillon-sur-Seine)"),
+            vec![
+                "20",
+                "rue",
+                "de",
+                "l",
+                "eglise",
+                "chatillon",
+                "sur",
+                "seine"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("vaureal", "vaureal"), 0);
+        assert_eq!(levenshtein("vaureaal", "vaureal"), 1);
+        assert_eq!(levenshtein("vaureal", "vauriel"), 2);
+        assert_eq!(levenshtein("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_minimum_should_match() {
+        assert_eq!(minimum_should_match(1), 1);
+        assert_eq!(minimum_should_match(3), 2);
+        assert_eq!(minimum_should_match(5), 3);
+        assert_eq!(minimum_should_match(12), 8);
+        assert_eq!(minimum_should_match(40), 10);
+    }
+
+    #[test]
+    fn test_matches_all_words() {
+        assert!(matches_all_words(
+            "rue de la Reine",
+            Some("Rue de la Reine Blanche")
+        ));
+        assert!(matches_all_words("77000", vec!["77003", "77000"]));
+        assert!(matches_all_words(
+            "Île-de-France",
+            vec!["Melun", "ile de france"]
+        ));
+        assert!(!matches_all_words(
+            "rue du roi",
+            Some("Rue de la Reine Blanche")
+        ));
+        assert!(!matches_all_words("", Some("Melun")));
+    }
+
+    #[test]
+    fn test_matches_house_number() {
+        let words = tokenize("12 bis rue de la Reine");
+        assert!(matches_house_number("12 bis", &words));
+        assert!(matches_house_number("12bis", &words));
+        assert!(matches_house_number("12", &words));
+        assert!(!matches_house_number("12 ter", &words));
+        assert!(!matches_house_number("2", &words));
+        let words = tokenize("12ter rue de la Reine");
+        assert!(matches_house_number("12 ter", &words));
+        assert!(matches_house_number("12ter", &words));
+        assert!(!matches_house_number("12", &words));
+    }
+}
@@ -35,8 +35,15 @@
 // #[macro_use]
 // extern crate failure;
 
+pub mod admin_geofinder;
+pub mod embedded;
+pub mod node_pool;
 pub mod objects;
+pub mod query;
+pub mod ranking;
 pub mod rubber;
+pub mod store;
+pub mod synonyms;
 
 pub use crate::objects::*;
 use slog::{self, o, slog_o, Drain, Never};
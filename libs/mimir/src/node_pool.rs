@@ -0,0 +1,147 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+//! Pool of the elasticsearch nodes used by a `Rubber`
+//!
+//! The requests are spread over the nodes in a round robin way. A node failing to answer
+//! is marked unhealthy, and is not used for some time, unless all the nodes are unhealthy.
+//! The pool only knows the nodes by their position in the connection string, so that the
+//! rubbers with different timeouts (thus different clients) can share it.
+
+use slog::slog_warn;
+use slog_scope::warn;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Time during which a node is not used after a failure
+const UNHEALTHY_NODE_DELAY: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+struct Node {
+    // the node is not used before this instant
+    unhealthy_until: Mutex<Option<Instant>>,
+}
+
+impl Node {
+    fn is_healthy(&self, now: Instant) -> bool {
+        self.unhealthy_until
+            .lock()
+            .unwrap()
+            .map_or(true, |until| until <= now)
+    }
+}
+
+#[derive(Debug)]
+pub struct NodePool {
+    nodes: Vec<Node>,
+    next: AtomicUsize,
+}
+
+impl NodePool {
+    /// a pool of `nb_nodes` nodes (there must be at least one)
+    pub fn new(nb_nodes: usize) -> NodePool {
+        assert!(nb_nodes > 0, "no elasticsearch node");
+        NodePool {
+            nodes: (0..nb_nodes)
+                .map(|_| Node {
+                    unhealthy_until: Mutex::new(None),
+                })
+                .collect(),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// the next healthy node
+    /// (if all the nodes are unhealthy, the next one is used anyway)
+    pub fn next_node(&self) -> usize {
+        let now = Instant::now();
+        let first = self.next.fetch_add(1, Ordering::Relaxed);
+        let node = (0..self.nodes.len())
+            .map(|i| (first + i) % self.nodes.len())
+            .find(|&node| self.nodes[node].is_healthy(now))
+            .unwrap_or(first % self.nodes.len());
+        if node != first % self.nodes.len() {
+            // the following requests start after this node
+            self.next.store(node + 1, Ordering::Relaxed);
+        }
+        node
+    }
+
+    pub fn mark_unhealthy(&self, node: usize) {
+        if self.nodes.len() > 1 {
+            // the url of the node is not logged since it can contain a password
+            warn!(
+                "elasticsearch node {} (of the connection string) is unhealthy, it is not used for {:?}",
+                node + 1,
+                UNHEALTHY_NODE_DELAY
+            );
+        }
+        *self.nodes[node].unhealthy_until.lock().unwrap() =
+            Some(Instant::now() + UNHEALTHY_NODE_DELAY);
+    }
+
+    pub fn mark_healthy(&self, node: usize) {
+        *self.nodes[node].unhealthy_until.lock().unwrap() = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_robin() {
+        let pool = NodePool::new(3);
+        let nodes: Vec<_> = (0..5).map(|_| pool.next_node()).collect();
+        assert_eq!(nodes, vec![0, 1, 2, 0, 1]);
+    }
+
+    #[test]
+    fn test_unhealthy_node() {
+        let pool = NodePool::new(3);
+        pool.mark_unhealthy(1);
+        let nodes: Vec<_> = (0..4).map(|_| pool.next_node()).collect();
+        assert_eq!(nodes, vec![0, 2, 0, 2]);
+
+        pool.mark_healthy(1);
+        let nodes: Vec<_> = (0..3).map(|_| pool.next_node()).collect();
+        assert_eq!(nodes, vec![0, 1, 2]);
+
+        // if all the nodes are unhealthy, they are all used anyway
+        (0..3).for_each(|node| pool.mark_unhealthy(node));
+        let nodes: Vec<_> = (0..3).map(|_| pool.next_node()).collect();
+        assert_eq!(nodes, vec![0, 1, 2]);
+    }
+}
@@ -41,13 +41,93 @@ use std::fmt;
 use std::iter::FromIterator;
 use std::rc::Rc;
 use std::sync::Arc;
-use transit_model::objects::Rgb;
+pub use transit_model::objects::Rgb;
 
 pub trait Incr: Clone {
     fn id(&self) -> &str;
     fn incr(&mut self);
 }
 
+/// The kinds of places
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum PlaceKind {
+    Admin,
+    Street,
+    Addr,
+    Poi,
+    Stop,
+    Line,
+}
+
+impl PlaceKind {
+    pub const ALL: [PlaceKind; 6] = [
+        PlaceKind::Admin,
+        PlaceKind::Street,
+        PlaceKind::Addr,
+        PlaceKind::Poi,
+        PlaceKind::Stop,
+        PlaceKind::Line,
+    ];
+
+    /// the elasticsearch type name (the `type` field of the typeless mappings)
+    pub fn es_name(self) -> &'static str {
+        match self {
+            PlaceKind::Admin => "admin",
+            PlaceKind::Street => "street",
+            PlaceKind::Addr => "addr",
+            PlaceKind::Poi => "poi",
+            PlaceKind::Stop => "stop",
+            PlaceKind::Line => "line",
+        }
+    }
+
+    /// the name used by the api, in the `type` of the responses and the `type[]` parameter
+    pub fn api_name(self) -> &'static str {
+        match self {
+            PlaceKind::Admin => "zone",
+            PlaceKind::Street => "street",
+            PlaceKind::Addr => "house",
+            PlaceKind::Poi => "poi",
+            PlaceKind::Stop => "public_transport:stop_area",
+            PlaceKind::Line => "public_transport:line",
+        }
+    }
+
+    /// the prefix of the names of the indexes of this kind
+    /// (this is also the alias over all the public indexes of this kind)
+    pub fn index_prefix(self) -> &'static str {
+        match self {
+            PlaceKind::Admin => "munin_admin",
+            PlaceKind::Street => "munin_street",
+            PlaceKind::Addr => "munin_addr",
+            PlaceKind::Poi => "munin_poi",
+            PlaceKind::Stop => "munin_stop",
+            PlaceKind::Line => "munin_line",
+        }
+    }
+
+    pub fn from_es_name(name: &str) -> Option<PlaceKind> {
+        PlaceKind::ALL.iter().cloned().find(|k| k.es_name() == name)
+    }
+
+    /// the `city` api type is also an admin
+    pub fn from_api_name(name: &str) -> Option<PlaceKind> {
+        match name {
+            "city" => Some(PlaceKind::Admin),
+            _ => PlaceKind::ALL
+                .iter()
+                .cloned()
+                .find(|k| k.api_name() == name),
+        }
+    }
+}
+
+impl fmt::Display for PlaceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.es_name())
+    }
+}
+
 /// Object stored in elastic search
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum Place {
@@ -56,6 +136,7 @@ pub enum Place {
     Addr(Addr),
     Poi(Poi),
     Stop(Stop),
+    Line(Line),
 }
 
 /// Object stored in elastic search
@@ -67,6 +148,17 @@ pub enum Address {
 }
 
 impl Place {
+    pub fn kind(&self) -> PlaceKind {
+        match *self {
+            Place::Admin(_) => PlaceKind::Admin,
+            Place::Street(_) => PlaceKind::Street,
+            Place::Addr(_) => PlaceKind::Addr,
+            Place::Poi(_) => PlaceKind::Poi,
+            Place::Stop(_) => PlaceKind::Stop,
+            Place::Line(_) => PlaceKind::Line,
+        }
+    }
+
     pub fn is_admin(&self) -> bool {
         match *self {
             Place::Admin(_) => true,
@@ -97,6 +189,12 @@ impl Place {
             _ => false,
         }
     }
+    pub fn is_line(&self) -> bool {
+        match *self {
+            Place::Line(_) => true,
+            _ => false,
+        }
+    }
     pub fn poi(&self) -> Option<&Poi> {
         match *self {
             Place::Poi(ref poi) => Some(poi),
@@ -111,6 +209,7 @@ impl Place {
             Place::Addr(ref o) => o.label(),
             Place::Poi(ref o) => o.label(),
             Place::Stop(ref o) => o.label(),
+            Place::Line(ref o) => o.label(),
         }
     }
 
@@ -121,6 +220,7 @@ impl Place {
             Place::Addr(ref o) => o.admins(),
             Place::Poi(ref o) => o.admins(),
             Place::Stop(ref o) => o.admins(),
+            Place::Line(ref o) => o.admins(),
         }
     }
 
@@ -131,6 +231,7 @@ impl Place {
             Place::Addr(ref o) => Some(Address::Addr(o.clone())),
             Place::Poi(_) => None,
             Place::Stop(_) => None,
+            Place::Line(_) => None,
         }
     }
 
@@ -141,6 +242,7 @@ impl Place {
             Place::Addr(ref o) => o.distance,
             Place::Poi(ref o) => o.distance,
             Place::Stop(ref o) => o.distance,
+            Place::Line(ref o) => o.distance,
         }
     }
 
@@ -151,6 +253,7 @@ impl Place {
             Place::Addr(ref mut o) => o.distance = Some(d),
             Place::Poi(ref mut o) => o.distance = Some(d),
             Place::Stop(ref mut o) => o.distance = Some(d),
+            Place::Line(ref mut o) => o.distance = Some(d),
         }
     }
 
@@ -161,6 +264,7 @@ impl Place {
             Place::Addr(ref o) => &o.coord,
             Place::Poi(ref o) => &o.coord,
             Place::Stop(ref o) => &o.coord,
+            Place::Line(ref o) => &o.coord,
         }
     }
 
@@ -171,6 +275,7 @@ impl Place {
             Place::Addr(ref mut o) => o.context = Some(context),
             Place::Poi(ref mut o) => o.context = Some(context),
             Place::Stop(ref mut o) => o.context = Some(context),
+            Place::Line(ref mut o) => o.context = Some(context),
         }
     }
 
@@ -183,13 +288,18 @@ impl Place {
             Place::Addr(ref o) => o.context.clone(),
             Place::Poi(ref o) => o.context.clone(),
             Place::Stop(ref o) => o.context.clone(),
+            Place::Line(ref o) => o.context.clone(),
         }
     }
 }
 
 pub trait MimirObject: serde::Serialize {
     fn is_geo_data() -> bool;
-    fn doc_type() -> &'static str; // provides the elasticsearch type name
+    fn kind() -> PlaceKind;
+    // provides the elasticsearch type name
+    fn doc_type() -> &'static str {
+        Self::kind().es_name()
+    }
     fn es_id(&self) -> Option<String>; // provides the elasticsearch id
 }
 
@@ -202,8 +312,8 @@ impl<'a, T: MimirObject> MimirObject for &'a T {
     fn is_geo_data() -> bool {
         T::is_geo_data()
     }
-    fn doc_type() -> &'static str {
-        T::doc_type()
+    fn kind() -> PlaceKind {
+        T::kind()
     }
     fn es_id(&self) -> Option<String> {
         T::es_id(self)
@@ -214,8 +324,8 @@ impl<T: MimirObject> MimirObject for Rc<T> {
     fn is_geo_data() -> bool {
         T::is_geo_data()
     }
-    fn doc_type() -> &'static str {
-        T::doc_type()
+    fn kind() -> PlaceKind {
+        T::kind()
     }
     fn es_id(&self) -> Option<String> {
         T::es_id(self)
@@ -288,8 +398,8 @@ impl MimirObject for Poi {
     fn is_geo_data() -> bool {
         true
     }
-    fn doc_type() -> &'static str {
-        "poi"
+    fn kind() -> PlaceKind {
+        PlaceKind::Poi
     }
     fn es_id(&self) -> Option<String> {
         Some(self.id.clone())
@@ -329,7 +439,11 @@ pub struct Code {
     pub value: String,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Default)]
+/// A public transport line
+///
+/// The lines of a stop only have the description of the line, the other fields
+/// are only set for the lines indexed as places (by `ntfs2mimir`).
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Line {
     pub id: String,
     pub name: String,
@@ -346,6 +460,60 @@ pub struct Line {
     pub physical_modes: Vec<PhysicalMode>,
     #[serde(skip_serializing)]
     pub sort_order: Option<u32>, // we do not serialise this field, it is only used to sort the Lines
+
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub label: String,
+    /// center of the bbox of the stop areas of the line
+    #[serde(default, skip_serializing_if = "Coord::is_default")]
+    pub coord: Coord,
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub approx_coord: Option<Geometry>,
+    /// bbox of the stop areas served by the line
+    #[serde(
+        serialize_with = "serialize_rect",
+        deserialize_with = "deserialize_rect",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub bbox: Option<geo_types::Rect<f64>>,
+    /// ids of the stop areas served by the line
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop_areas: Vec<String>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub weight: f64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub coverages: Vec<String>,
+    /// Distance to the coord in query.
+    /// Not serialized as is because it is returned in the `Feature` object
+    #[serde(default, skip)]
+    pub distance: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<Context>,
+}
+
+fn is_zero(weight: &f64) -> bool {
+    *weight == 0.
+}
+
+impl MimirObject for Line {
+    fn is_geo_data() -> bool {
+        false
+    }
+    fn kind() -> PlaceKind {
+        PlaceKind::Line
+    }
+    fn es_id(&self) -> Option<String> {
+        Some(self.id.clone())
+    }
+}
+
+impl Members for Line {
+    fn label(&self) -> &str {
+        &self.label
+    }
+    fn admins(&self) -> Vec<Arc<Admin>> {
+        vec![]
+    }
 }
 
 pub trait FromTransitModel<T> {
@@ -390,6 +558,7 @@ impl FromTransitModel<transit_model::objects::Line> for Line {
                     }
                 })
                 .collect(),
+            ..Default::default()
         }
     }
 }
@@ -518,8 +687,8 @@ impl MimirObject for Stop {
     fn is_geo_data() -> bool {
         false
     }
-    fn doc_type() -> &'static str {
-        "stop"
+    fn kind() -> PlaceKind {
+        PlaceKind::Stop
     }
     fn es_id(&self) -> Option<String> {
         Some(self.id.clone())
@@ -705,8 +874,8 @@ impl MimirObject for Admin {
     fn is_geo_data() -> bool {
         true
     }
-    fn doc_type() -> &'static str {
-        "admin"
+    fn kind() -> PlaceKind {
+        PlaceKind::Admin
     }
     fn es_id(&self) -> Option<String> {
         Some(self.id.clone())
@@ -748,8 +917,8 @@ impl MimirObject for Street {
     fn is_geo_data() -> bool {
         true
     }
-    fn doc_type() -> &'static str {
-        "street"
+    fn kind() -> PlaceKind {
+        PlaceKind::Street
     }
     fn es_id(&self) -> Option<String> {
         Some(self.id.clone())
@@ -794,8 +963,8 @@ impl MimirObject for Addr {
     fn is_geo_data() -> bool {
         true
     }
-    fn doc_type() -> &'static str {
-        "addr"
+    fn kind() -> PlaceKind {
+        PlaceKind::Addr
     }
     fn es_id(&self) -> Option<String> {
         Some(self.id.clone())
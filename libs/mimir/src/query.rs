@@ -0,0 +1,667 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+//! Elasticsearch queries used to search the places
+
+use crate::objects::{Admin, Coord, MimirObject, Place, PlaceKind, Stop};
+use crate::ranking::Decay;
+use crate::rubber::{get_indexes, read_places, MappingMode, Rubber};
+use crate::store::{MatchType, ReverseQuery, SearchQuery, StructuredQuery};
+use cosmogony::ZoneType;
+use rs_es::error::EsError;
+use rs_es::operations::search::Source;
+use rs_es::query::compound::BoostMode;
+use rs_es::query::functions::{DecayOptions, Function, Modifier};
+use rs_es::query::Query;
+use rs_es::units as rs_u;
+use slog::slog_debug;
+use slog_scope::debug;
+use std::iter;
+
+// filter to handle PT coverages
+// we either want:
+// * to get objects with no coverage at all (non-PT objects)
+// * or the objects with coverage matching the ones we're allowed to get
+fn build_coverage_condition(pt_datasets: &[&str]) -> Query {
+    Query::build_bool()
+        .with_should(vec![
+            Query::build_bool()
+                .with_must_not(Query::build_exists("coverages").build())
+                .build(),
+            Query::build_terms("coverages")
+                .with_values(pt_datasets)
+                .build(),
+        ])
+        .build()
+}
+
+/// Create a `rs_es::Query` that boosts results according to the
+/// distance to `coord`.
+fn build_proximity_with_boost(coord: &Coord, decay: &Decay, weight: f64) -> Query {
+    Query::build_function_score()
+        .with_functions(vec![
+            DecayOptions::new(
+                rs_u::Location::LatLon(coord.lat(), coord.lon()),
+                rs_u::Distance::new(decay.scale_km, rs_u::DistanceUnit::Kilometer),
+            )
+            .with_offset(rs_u::Distance::new(
+                decay.offset_km,
+                rs_u::DistanceUnit::Kilometer,
+            ))
+            .with_decay(decay.decay)
+            .build("coord")
+            .build_exp(),
+            Function::build_weight(weight).build(),
+        ])
+        .with_boost_mode(BoostMode::Replace)
+        .build()
+}
+
+fn build_with_weight(factor: f64) -> Query {
+    Query::build_function_score()
+        .with_function(
+            Function::build_field_value_factor("weight")
+                .with_factor(factor)
+                .with_missing(0.)
+                .build(),
+        )
+        .with_boost_mode(BoostMode::Replace)
+        .build()
+}
+
+pub fn build_query(
+    search: &SearchQuery<'_>,
+    match_type: MatchType,
+    mapping_mode: MappingMode,
+) -> Query {
+    let q = search.q;
+    let doc_type_field = mapping_mode.doc_type_field();
+    let coord = search.coord;
+    let langs = search.langs;
+    let profile = search.profile();
+    let parsed = search.parsed.cloned().unwrap_or_default();
+    let boosts = &profile.fields;
+    let importance = &profile.importance;
+
+    // Priorization by type
+    let type_query = Query::build_bool()
+        .with_should(
+            PlaceKind::ALL
+                .iter()
+                .map(|kind| {
+                    Query::build_term(doc_type_field, kind.es_name())
+                        .with_boost(profile.types.get(*kind))
+                        .build()
+                })
+                .collect::<Vec<_>>(),
+        )
+        .with_boost(30.)
+        .build();
+
+    let format_names_field = |lang: &str| format!("names.{}", lang);
+    let format_labels_field = |lang: &str| format!("labels.{}", lang);
+    let format_labels_prefix_field = |lang: &str| format!("labels.{}.prefix", lang);
+
+    let build_multi_match = |default_field: &str, lang_field_formatter: &dyn Fn(&str) -> String| {
+        let boosted_i18n_fields = langs.iter().map(|lang| lang_field_formatter(lang));
+        let fields: Vec<String> = iter::once(default_field.into())
+            .chain(boosted_i18n_fields)
+            .collect();
+        Query::build_multi_match(fields, q)
+    };
+
+    // Priorization by query string
+    let mut string_should = vec![
+        build_multi_match("name", &format_names_field)
+            .with_boost(boosts.name)
+            .build(),
+        build_multi_match("label", &format_labels_field)
+            .with_boost(boosts.label)
+            .build(),
+        build_multi_match("label.prefix", &format_labels_prefix_field)
+            .with_boost(boosts.label_prefix)
+            .build(),
+        Query::build_match("zip_codes", q)
+            .with_boost(boosts.zip_codes)
+            .build(),
+    ];
+    match parsed.house_number {
+        // the house number recognized in the query
+        Some(ref house_number) => string_should.push(
+            Query::build_match("house_number", house_number.as_str())
+                .with_operator("and")
+                .with_boost(boosts.house_number)
+                .build(),
+        ),
+        None => string_should.push(
+            Query::build_match("house_number", q)
+                .with_boost(boosts.house_number)
+                .build(),
+        ),
+    }
+    if let MatchType::Fuzzy = match_type {
+        let format_labels_ngram_field = |lang: &str| format!("labels.{}.ngram", lang);
+        let ngram_boost = if coord.is_some() {
+            boosts.label_ngram_with_coord
+        } else {
+            boosts.label_ngram
+        };
+        string_should.push(
+            build_multi_match("label.ngram", &format_labels_ngram_field)
+                .with_boost(ngram_boost)
+                .build(),
+        );
+    }
+    let string_query = Query::build_bool()
+        .with_should(string_should)
+        .with_boost(1.)
+        .build();
+
+    let mut admin_weight = importance.admin_weight;
+
+    // Priorization by importance
+    let mut importance_queries = if let Some(ref coord) = coord {
+        let proximity =
+            build_proximity_with_boost(coord, &profile.decay, importance.proximity_weight);
+        if let MatchType::Fuzzy = match_type {
+            vec![build_with_weight(importance.weight_factor), proximity]
+        } else {
+            admin_weight = importance.admin_weight_with_coord;
+            vec![
+                build_with_weight(importance.weight_factor_with_coord),
+                proximity,
+            ]
+        }
+    } else {
+        vec![build_with_weight(importance.weight_factor)]
+    };
+
+    match match_type {
+        MatchType::Prefix => {
+            let admin_importance_query = Query::build_function_score()
+                .with_query(Query::build_term(doc_type_field, Admin::doc_type()).build())
+                .with_functions(vec![
+                    Function::build_field_value_factor("weight")
+                        .with_factor(1e6)
+                        .with_modifier(Modifier::Log1p)
+                        .with_missing(0.)
+                        .build(),
+                    Function::build_weight(admin_weight).build(),
+                ])
+                .with_boost_mode(BoostMode::Replace)
+                .build();
+            importance_queries.push(admin_importance_query);
+        }
+        MatchType::Fuzzy => {}
+    };
+
+    // filter to handle house number
+    // we either want:
+    // * to exactly match the document house_number
+    //   (the one recognized in the query if any, else one of the words of the query)
+    // * or that the document has no house_number
+    let house_number_matches = if parsed.house_number.is_some() {
+        parsed
+            .house_number_forms()
+            .into_iter()
+            .map(|number| {
+                Query::build_match("house_number", number)
+                    .with_operator("and")
+                    .build()
+            })
+            .collect()
+    } else {
+        vec![Query::build_match("house_number", q.to_string()).build()]
+    };
+    let house_number_condition = Query::build_bool()
+        .with_should(
+            iter::once(
+                Query::build_bool()
+                    .with_must_not(Query::build_exists("house_number").build())
+                    .build(),
+            )
+            .chain(house_number_matches)
+            .collect::<Vec<_>>(),
+        )
+        .build();
+
+    use rs_es::query::CombinationMinimumShouldMatch;
+    use rs_es::query::MinimumShouldMatch;
+
+    let matching_condition = match match_type {
+        // When the match type is Prefix, we want to use every possible information even though
+        // these are not present in label, for instance, the zip_code.
+        // The field full_label contains all of them and will do the trick.
+        MatchType::Prefix => Query::build_match("full_label.prefix".to_string(), q.to_string())
+            .with_operator("and")
+            .build(),
+        // for fuzzy search we lower our expectation & we accept a certain percentage of token match
+        // on full_label.ngram
+        // The values defined here are empirical,
+        // it's supposed to be able to manage cases BOTH missspelt one-word
+        // www.elastic.co/guide/en/elasticsearch/guide/current/match-multi-word.html#match-precision
+        // requests AND very long requests.
+        // Missspelt one-word request:
+        //     Vaureaaal (instead of Vaureal)
+        // Very long requests:
+        //     Caisse Primaire d'Assurance Maladie de Haute Garonne, 33 Rue du Lot, 31100 Toulouse
+        MatchType::Fuzzy => Query::build_match("full_label.ngram".to_string(), q.to_string())
+            .with_minimum_should_match(MinimumShouldMatch::from(vec![
+                CombinationMinimumShouldMatch::new(1i64, -1i64),
+                CombinationMinimumShouldMatch::new(3i64, -2i64),
+                CombinationMinimumShouldMatch::new(9i64, -4i64),
+                CombinationMinimumShouldMatch::new(20i64, 25f64),
+            ]))
+            .build(),
+    };
+
+    let mut filters = vec![house_number_condition, matching_condition];
+
+    // the postcode recognized in the query must be one of the zip codes of the document
+    // (if it has some)
+    if let Some(ref postcode) = parsed.postcode {
+        filters.push(
+            Query::build_bool()
+                .with_should(vec![
+                    Query::build_bool()
+                        .with_must_not(Query::build_exists("zip_codes").build())
+                        .build(),
+                    Query::build_match("zip_codes", postcode.as_str()).build(),
+                ])
+                .build(),
+        );
+    }
+
+    // the city recognized in the query must be one of the admins of the document,
+    // or the admin itself (the documents without indexed admins are kept)
+    if let Some(ref city) = parsed.city {
+        let admins_fields = [
+            "administrative_regions.name",
+            "street.administrative_regions.name",
+        ];
+        let match_city = |field: &str| {
+            Query::build_match(field.to_string(), city.to_string())
+                .with_operator("and")
+                .build()
+        };
+        let mut city_condition: Vec<Query> = admins_fields.iter().map(|f| match_city(f)).collect();
+        city_condition.push(
+            Query::build_bool()
+                .with_must(vec![
+                    Query::build_term(doc_type_field, Admin::doc_type()).build(),
+                    match_city("name"),
+                ])
+                .build(),
+        );
+        city_condition.push(
+            Query::build_bool()
+                .with_must_not(
+                    admins_fields
+                        .iter()
+                        .map(|f| Query::build_exists(*f).build())
+                        .chain(iter::once(
+                            Query::build_term(doc_type_field, Admin::doc_type()).build(),
+                        ))
+                        .collect::<Vec<_>>(),
+                )
+                .build(),
+        );
+        filters.push(Query::build_bool().with_should(city_condition).build());
+    }
+
+    // if searching through all data, no coverage filter
+    if !search.all_data {
+        filters.push(build_coverage_condition(search.pt_datasets));
+    }
+
+    // We want to limit the search to the geographic shape given in argument,
+    // except for stop areas
+    if let Some(s) = search.shape.clone() {
+        let filter_wo_stop = Query::build_bool()
+            .with_must(vec![
+                Query::build_bool()
+                    .with_must_not(Query::build_term(doc_type_field, Stop::doc_type()).build())
+                    .build(),
+                Query::build_geo_shape("approx_coord")
+                    .with_geojson(s)
+                    .build(),
+            ])
+            .build();
+        let filter_w_stop = Query::build_term(doc_type_field, Stop::doc_type()).build();
+        let geo_filter = Query::build_bool()
+            .with_should(vec![filter_w_stop, filter_wo_stop])
+            .build();
+        filters.push(geo_filter);
+    }
+
+    let mut query = Query::build_bool()
+        .with_must(vec![type_query, string_query])
+        .with_should(importance_queries)
+        .with_filter(Query::build_bool().with_must(filters).build());
+
+    if !search.zone_types.is_empty() {
+        query = query.with_filter(
+            Query::build_bool()
+                .with_should(
+                    search
+                        .zone_types
+                        .iter()
+                        .map(|x| Query::build_match("zone_type", *x).build())
+                        .collect::<Vec<_>>(),
+                )
+                .build(),
+        );
+    }
+    if !search.poi_types.is_empty() {
+        query = query.with_filter(
+            Query::build_bool()
+                .with_should(
+                    search
+                        .poi_types
+                        .iter()
+                        .map(|x| Query::build_match("poi_type.id", *x).build())
+                        .collect::<Vec<_>>(),
+                )
+                .build(),
+        );
+    }
+
+    query.build()
+}
+
+/// search the places matching the query in elasticsearch
+pub fn search(
+    rubber: &mut Rubber,
+    search: &SearchQuery<'_>,
+    match_type: MatchType,
+) -> Result<Vec<Place>, EsError> {
+    let query = build_query(search, match_type, rubber.mapping_mode);
+
+    let indexes = get_indexes(
+        search.all_data,
+        search.pt_datasets,
+        search.poi_datasets,
+        search.types,
+    )
+    .map_err(|e| EsError::EsError(e.to_string()))?;
+    let indexes = indexes
+        .iter()
+        .map(|index| index.as_str())
+        .collect::<Vec<&str>>();
+    debug!("ES indexes: {:?}", indexes);
+
+    if indexes.is_empty() {
+        // if there is no indexes, rs_es search with index "_all"
+        // but we want to return empty response in this case.
+        return Ok(vec![]);
+    }
+
+    let timeout = rubber.timeout.map(|t| format!("{:?}", t));
+    let mut search_query = rubber.es_client.search_query();
+
+    let search_query = search_query
+        .with_ignore_unavailable(true)
+        .with_indexes(&indexes)
+        .with_query(&query)
+        .with_from(search.offset)
+        .with_size(search.limit)
+        // No need to fetch "boundary" as it's not used in the geocoding response
+        // and is very large in some documents (countries...)
+        .with_source(Source::exclude(&["boundary"]));
+
+    // We don't want to clutter the Query URL, so we only add an explanation if the option is used
+    let search_query = match search.debug {
+        true => search_query.with_explain(true),
+        false => search_query,
+    };
+
+    if let Some(timeout) = &timeout {
+        search_query.with_timeout(timeout.as_str());
+    }
+    let result = search_query.send()?;
+
+    read_places(result, search.coord.as_ref())
+}
+
+/// the query of the places matching each part of a structured geocoding
+///
+/// All the words of each part must be found in its field: the house number,
+/// the name of the street, the zip codes, and the names of the admins (or the
+/// country codes for the country).
+/// When there is no street, the searched places are the cities (or the countries
+/// if there is only a country).
+pub fn build_structured_query(structured: &StructuredQuery<'_>) -> Query {
+    let kind = structured.kind();
+    let match_all_words = |field: &str, value: &str| {
+        Query::build_match(field.to_string(), value.to_string())
+            .with_operator("and")
+            .build()
+    };
+    // the name of an admin, in the default language or in one of the languages of the query
+    let match_admin_name = |value: &str| {
+        let fields: Vec<String> = iter::once("name".to_string())
+            .chain(structured.langs.iter().map(|l| format!("names.{}", l)))
+            .collect();
+        Query::build_multi_match(fields, value)
+            .with_operator("and")
+            .build()
+    };
+    let admins_field = match kind {
+        PlaceKind::Addr => "street.administrative_regions.name",
+        _ => "administrative_regions.name",
+    };
+    let is_city = structured.city.is_some() || structured.postcode.is_some();
+
+    let mut must = vec![];
+    if let Some(house_number) = structured.house_number {
+        must.push(match_all_words("house_number", house_number));
+    }
+    if let Some(street) = structured.street {
+        let field = match kind {
+            PlaceKind::Addr => "street.name",
+            _ => "name",
+        };
+        must.push(match_all_words(field, street));
+    }
+    if let Some(postcode) = structured.postcode {
+        must.push(match_all_words("zip_codes", postcode));
+    }
+    if let Some(city) = structured.city {
+        must.push(match kind {
+            PlaceKind::Admin => match_admin_name(city),
+            _ => match_all_words(admins_field, city),
+        });
+    }
+    if let Some(country) = structured.country {
+        let country_name = if kind == PlaceKind::Admin && !is_city {
+            match_admin_name(country)
+        } else {
+            match_all_words(admins_field, country)
+        };
+        must.push(
+            Query::build_bool()
+                .with_should(vec![
+                    country_name,
+                    match_all_words("country_codes", country),
+                ])
+                .build(),
+        );
+    }
+
+    let mut query = Query::build_bool()
+        .with_must(must)
+        .with_should(build_with_weight(
+            structured.profile().importance.weight_factor,
+        ));
+    if kind == PlaceKind::Admin {
+        let zone_type = if is_city {
+            ZoneType::City
+        } else {
+            ZoneType::Country
+        };
+        query = query.with_filter(Query::build_term("zone_type", zone_type.as_str()).build());
+    }
+    query.build()
+}
+
+/// search the places matching each part of a structured query in elasticsearch
+pub fn search_structured(
+    rubber: &mut Rubber,
+    structured: &StructuredQuery<'_>,
+) -> Result<Vec<Place>, EsError> {
+    let query = build_structured_query(structured);
+    let indexes = [structured.kind().index_prefix()];
+    debug!("ES indexes: {:?}", indexes);
+
+    let timeout = rubber.timeout.map(|t| format!("{:?}", t));
+    let mut search_query = rubber.es_client.search_query();
+
+    let search_query = search_query
+        .with_ignore_unavailable(true)
+        .with_indexes(&indexes)
+        .with_query(&query)
+        .with_from(structured.offset)
+        .with_size(structured.limit)
+        .with_source(Source::exclude(&["boundary"]));
+
+    if let Some(timeout) = &timeout {
+        search_query.with_timeout(timeout.as_str());
+    }
+    let result = search_query.send()?;
+
+    read_places(result, None)
+}
+
+/// get the places with the given id in elasticsearch
+pub fn features(
+    rubber: &mut Rubber,
+    pt_datasets: &[&str],
+    poi_datasets: &[&str],
+    all_data: bool,
+    id: &str,
+) -> Result<Vec<Place>, EsError> {
+    let val = rs_es::units::JsonVal::String(id.into());
+    let mut filters = vec![Query::build_ids(vec![val]).build()];
+
+    // if searching through all data, no coverage filter
+    if !all_data {
+        filters.push(build_coverage_condition(pt_datasets));
+    }
+    let filter = Query::build_bool().with_must(filters).build();
+    let query = Query::build_bool().with_filter(filter).build();
+
+    let indexes = get_indexes(all_data, &pt_datasets, &poi_datasets, &[])
+        .map_err(|e| EsError::EsError(e.to_string()))?;
+    let indexes = indexes
+        .iter()
+        .map(|index| index.as_str())
+        .collect::<Vec<&str>>();
+
+    debug!("ES indexes: {:?}", indexes);
+
+    if indexes.is_empty() {
+        // if there is no indexes, rs_es search with index "_all"
+        // but we want to return nothing in this case.
+        return Ok(vec![]);
+    }
+
+    let timeout = rubber.timeout.map(|t| format!("{:?}", t));
+    let mut search_query = rubber.es_client.search_query();
+
+    let search_query = search_query
+        .with_ignore_unavailable(true)
+        .with_indexes(&indexes)
+        .with_query(&query);
+
+    if let Some(timeout) = &timeout {
+        search_query.with_timeout(timeout.as_str());
+    }
+
+    let result = search_query.send()?;
+
+    read_places(result, None)
+}
+
+/// the query of the places within the radius of the reverse geocoding,
+/// the nearest having the best score
+pub fn build_reverse_query(reverse: &ReverseQuery<'_>) -> Query {
+    let coord = &reverse.coord;
+    let distance = rs_u::Distance::new(reverse.radius, rs_u::DistanceUnit::Meter);
+    let geo_distance =
+        Query::build_geo_distance("coord", (coord.lat(), coord.lon()), distance).build();
+    Query::build_bool()
+        .with_should(crate::rubber::build_proximity_with_boost(coord, 1.))
+        .with_must(geo_distance)
+        .with_filter(build_coverage_condition(reverse.pt_datasets))
+        .build()
+}
+
+/// get the places next to the coord of the query in elasticsearch, the nearest first
+pub fn reverse(rubber: &mut Rubber, reverse: &ReverseQuery<'_>) -> Result<Vec<Place>, EsError> {
+    let indexes = get_indexes(
+        false,
+        reverse.pt_datasets,
+        reverse.poi_datasets,
+        reverse.types,
+    )
+    .map_err(|e| EsError::EsError(e.to_string()))?;
+    let indexes = indexes
+        .iter()
+        .map(|index| index.as_str())
+        .collect::<Vec<&str>>();
+    debug!("ES indexes: {:?}", indexes);
+
+    if indexes.is_empty() {
+        // if there is no indexes, rs_es search with index "_all"
+        // but we want to return nothing in this case.
+        return Ok(vec![]);
+    }
+
+    let query = build_reverse_query(reverse);
+    let timeout = rubber.timeout.map(|t| format!("{:?}", t));
+    let mut search_query = rubber.es_client.search_query();
+
+    let search_query = search_query
+        .with_ignore_unavailable(true)
+        .with_indexes(&indexes)
+        .with_query(&query)
+        .with_size(reverse.limit)
+        .with_source(Source::exclude(&["boundary"]));
+
+    if let Some(timeout) = &timeout {
+        search_query.with_timeout(timeout.as_str());
+    }
+    let result = search_query.send()?;
+
+    let mut places = read_places(result, Some(&reverse.coord))?;
+    // the score already decreases with the distance, this only makes the order exact
+    places.sort_by_key(|place| place.distance());
+    Ok(places)
+}
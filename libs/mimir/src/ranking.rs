@@ -0,0 +1,333 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+//! Ranking profiles: the relevance constants of the autocomplete.
+//!
+//! The profiles are read from a json file, mapping the name of each profile to its constants,
+//! like `{"default": {...}, "pt_app": {"types": {"stop": 40}}}`.
+//! The missing constants of a profile keep their default value, and the `default` profile
+//! (the hard-coded constants if there is none in the file) is used when no profile is requested.
+
+use crate::objects::PlaceKind;
+use failure::{bail, format_err, Error, ResultExt};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// name of the profile used when no profile is requested
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Boost of each kind of place
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct TypeBoosts {
+    pub addr: f64,
+    pub admin: f64,
+    pub stop: f64,
+    pub line: f64,
+    pub poi: f64,
+    pub street: f64,
+}
+
+impl Default for TypeBoosts {
+    fn default() -> Self {
+        TypeBoosts {
+            addr: 30.,
+            admin: 19.,
+            stop: 18.,
+            line: 18.,
+            poi: 1.5,
+            street: 1.,
+        }
+    }
+}
+
+impl TypeBoosts {
+    pub fn get(&self, kind: PlaceKind) -> f64 {
+        match kind {
+            PlaceKind::Addr => self.addr,
+            PlaceKind::Admin => self.admin,
+            PlaceKind::Stop => self.stop,
+            PlaceKind::Line => self.line,
+            PlaceKind::Poi => self.poi,
+            PlaceKind::Street => self.street,
+        }
+    }
+
+    /// the highest boost, used to normalize the boosts
+    pub fn max(&self) -> f64 {
+        PlaceKind::ALL
+            .iter()
+            .map(|kind| self.get(*kind))
+            .fold(0., f64::max)
+    }
+}
+
+/// Boost of the match of the query string on each field
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct FieldBoosts {
+    pub name: f64,
+    pub label: f64,
+    pub label_prefix: f64,
+    pub zip_codes: f64,
+    pub house_number: f64,
+    /// the ngrams of the label, for a fuzzy search
+    pub label_ngram: f64,
+    /// the ngrams of the label, for a fuzzy search around a coord
+    pub label_ngram_with_coord: f64,
+}
+
+impl Default for FieldBoosts {
+    fn default() -> Self {
+        FieldBoosts {
+            name: 1.8,
+            label: 0.6,
+            label_prefix: 0.6,
+            zip_codes: 1.,
+            house_number: 0.001,
+            label_ngram: 1.8,
+            label_ngram_with_coord: 3.8,
+        }
+    }
+}
+
+/// Weight of the importance of the places: their `weight` and their distance to the coord
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Importance {
+    /// factor of the weight of the places
+    pub weight_factor: f64,
+    /// factor of the weight of the places, for a prefix search around a coord
+    pub weight_factor_with_coord: f64,
+    /// weight of the (log of the) weight of the admins, for a prefix search
+    pub admin_weight: f64,
+    /// weight of the (log of the) weight of the admins, for a prefix search around a coord
+    pub admin_weight_with_coord: f64,
+    /// weight of the proximity to the coord
+    pub proximity_weight: f64,
+}
+
+impl Default for Importance {
+    fn default() -> Self {
+        Importance {
+            weight_factor: 0.15,
+            weight_factor_with_coord: 0.4,
+            admin_weight: 0.03,
+            admin_weight_with_coord: 0.12,
+            proximity_weight: 0.4,
+        }
+    }
+}
+
+/// Exponential decay of the proximity with the distance to the coord
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Decay {
+    /// distance (in km) from the offset at which the proximity is `decay`
+    pub scale_km: f64,
+    /// distance (in km) under which the proximity is 1
+    pub offset_km: f64,
+    pub decay: f64,
+}
+
+impl Default for Decay {
+    fn default() -> Self {
+        Decay {
+            scale_km: 130.,
+            offset_km: 20.,
+            decay: 0.4,
+        }
+    }
+}
+
+impl Decay {
+    /// the proximity (between 0 and 1) of a place at `distance` meters
+    pub fn proximity(&self, distance: f64) -> f64 {
+        let distance = (distance - self.offset_km * 1000.).max(0.);
+        (self.decay.ln() / (self.scale_km * 1000.) * distance).exp()
+    }
+}
+
+/// The relevance constants of the autocomplete
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct RankingProfile {
+    pub types: TypeBoosts,
+    pub fields: FieldBoosts,
+    pub importance: Importance,
+    pub decay: Decay,
+}
+
+impl RankingProfile {
+    /// check that the constants can be used in a search
+    /// (the boosts and the weights are positive, the decay of the proximity is between 0 and 1)
+    pub fn validate(&self) -> Result<(), Error> {
+        let constants = serde_json::to_value(self)?;
+        for (group, values) in constants.as_object().into_iter().flatten() {
+            for (name, value) in values.as_object().into_iter().flatten() {
+                if value.as_f64().map_or(true, |v| v < 0.) {
+                    bail!("{}.{} must be positive, not {}", group, name, value);
+                }
+            }
+        }
+        if self.decay.decay <= 0. || self.decay.decay >= 1. {
+            bail!(
+                "decay.decay must be between 0 and 1 (excluded), not {}",
+                self.decay.decay
+            );
+        }
+        if self.decay.scale_km <= 0. {
+            bail!(
+                "decay.scale_km must be greater than 0, not {}",
+                self.decay.scale_km
+            );
+        }
+        Ok(())
+    }
+}
+
+/// the hard-coded relevance constants
+pub fn default_profile() -> &'static RankingProfile {
+    lazy_static::lazy_static! {
+        static ref DEFAULT: RankingProfile = RankingProfile::default();
+    }
+    &DEFAULT
+}
+
+/// The named ranking profiles
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct RankingProfiles(pub BTreeMap<String, RankingProfile>);
+
+impl RankingProfiles {
+    /// read the profiles, checking that their constants are valid
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let profiles: Self = serde_json::from_str(json)?;
+        for (name, profile) in &profiles.0 {
+            profile
+                .validate()
+                .map_err(|e| format_err!("invalid profile {}: {}", name, e))?;
+        }
+        Ok(profiles)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        let json = std::fs::read_to_string(path)
+            .with_context(|_| format!("impossible to read the ranking profiles {:?}", path))?;
+        Self::from_json(&json)
+            .map_err(|e| format_err!("invalid ranking profiles {:?}: {}", path, e))
+    }
+
+    /// the profile used when no profile is requested
+    pub fn default_profile(&self) -> &RankingProfile {
+        match self.0.get(DEFAULT_PROFILE) {
+            Some(profile) => profile,
+            None => default_profile(),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&RankingProfile> {
+        self.0.get(name)
+    }
+
+    /// the names of the profiles
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles() {
+        let profiles = RankingProfiles::from_json(
+            r#"{
+                "default": {"decay": {"scale_km": 50}},
+                "pt_app": {"types": {"stop": 40, "line": 40}, "importance": {"weight_factor": 0.3}}
+            }"#,
+        )
+        .unwrap();
+
+        let default = profiles.default_profile();
+        assert_eq!(default.decay.scale_km, 50.);
+        assert_eq!(default.decay.offset_km, 20.);
+        assert_eq!(default.types, TypeBoosts::default());
+
+        let pt_app = profiles.get("pt_app").unwrap();
+        assert_eq!(pt_app.types.get(PlaceKind::Stop), 40.);
+        assert_eq!(pt_app.types.get(PlaceKind::Addr), 30.);
+        assert_eq!(pt_app.types.max(), 40.);
+        assert_eq!(pt_app.importance.weight_factor, 0.3);
+        assert_eq!(pt_app.decay, Decay::default());
+
+        assert!(profiles.get("bob").is_none());
+        assert_eq!(
+            profiles.names().collect::<Vec<_>>(),
+            vec!["default", "pt_app"]
+        );
+
+        // without a file, the default profile is the hard-coded one
+        assert_eq!(
+            RankingProfiles::default().default_profile(),
+            &RankingProfile::default()
+        );
+
+        // the typos in the constants are not silently ignored
+        assert!(RankingProfiles::from_json(r#"{"default": {"types": {"stops": 40}}}"#).is_err());
+    }
+
+    #[test]
+    fn test_invalid_profiles() {
+        assert!(RankingProfile::default().validate().is_ok());
+        let error = |json: &str| RankingProfiles::from_json(json).unwrap_err().to_string();
+        assert_eq!(
+            error(r#"{"pt_app": {"decay": {"decay": 1.5}}}"#),
+            "invalid profile pt_app: decay.decay must be between 0 and 1 (excluded), not 1.5"
+        );
+        assert_eq!(
+            error(r#"{"default": {"decay": {"scale_km": 0}}}"#),
+            "invalid profile default: decay.scale_km must be greater than 0, not 0"
+        );
+        assert_eq!(
+            error(r#"{"default": {"types": {"poi": -1}}}"#),
+            "invalid profile default: types.poi must be positive, not -1.0"
+        );
+    }
+
+    #[test]
+    fn test_decay() {
+        let decay = Decay::default();
+        assert_eq!(decay.proximity(0.), 1.);
+        assert_eq!(decay.proximity(20_000.), 1.);
+        assert!((decay.proximity(150_000.) - 0.4).abs() < 1e-9);
+    }
+}
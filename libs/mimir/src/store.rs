@@ -0,0 +1,304 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use crate::objects::{Admin, Coord, MimirObject, Place, PlaceKind};
+use crate::ranking::{self, RankingProfile};
+use crate::rubber::{get_main_type_index, IndexDelta, IndexSettings, IndexVisibility, TypedIndex};
+use failure::Error;
+use geojson::Geometry;
+use serde::Serialize;
+use slog::slog_warn;
+use slog_scope::warn;
+use std::fmt;
+
+/// The kind of matching done on the query string
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum MatchType {
+    /// the words of the query must be prefixes of the words of the place
+    Prefix,
+    /// some words of the query can be misspelt or missing
+    Fuzzy,
+}
+
+impl fmt::Display for MatchType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let printable = match *self {
+            MatchType::Prefix => "prefix",
+            MatchType::Fuzzy => "fuzzy",
+        };
+        write!(f, "{}", printable)
+    }
+}
+
+/// The parts of an address recognized in the query string
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ParsedQuery {
+    /// the house number, with its suffix if any (`12 bis`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub house_number: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub postcode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub city: Option<String>,
+}
+
+impl ParsedQuery {
+    /// the forms of the house number that can be indexed (`12 bis` and `12bis`)
+    pub fn house_number_forms(&self) -> Vec<String> {
+        let mut forms: Vec<String> = self.house_number.iter().cloned().collect();
+        if let Some(ref number) = self.house_number {
+            if number.contains(' ') {
+                forms.push(number.replace(' ', ""));
+            }
+        }
+        forms
+    }
+}
+
+/// All the parameters of an autocomplete search
+#[derive(Debug, Clone, Default)]
+pub struct SearchQuery<'a> {
+    pub q: &'a str,
+    pub pt_datasets: &'a [&'a str],
+    pub poi_datasets: &'a [&'a str],
+    pub all_data: bool,
+    pub offset: u64,
+    pub limit: u64,
+    pub coord: Option<Coord>,
+    pub shape: Option<Geometry>,
+    pub types: &'a [&'a str],
+    pub zone_types: &'a [&'a str],
+    pub poi_types: &'a [&'a str],
+    pub langs: &'a [&'a str],
+    pub debug: bool,
+    /// the relevance constants, the default ones if none
+    pub profile: Option<&'a RankingProfile>,
+    /// the parts recognized in `q`, used to filter the places
+    pub parsed: Option<&'a ParsedQuery>,
+}
+
+impl<'a> SearchQuery<'a> {
+    /// the relevance constants of the search
+    pub fn profile(&self) -> &'a RankingProfile {
+        match self.profile {
+            Some(profile) => profile,
+            None => ranking::default_profile(),
+        }
+    }
+}
+
+/// default maximum distance (in meters) of the places found by a reverse geocoding
+pub const DEFAULT_REVERSE_RADIUS: f64 = 1000.;
+
+/// the types of the places searched by default by a reverse geocoding
+pub const ADDRESS_TYPES: &[&str] = &["house", "street"];
+
+/// All the parameters of a reverse geocoding
+#[derive(Debug, Clone)]
+pub struct ReverseQuery<'a> {
+    pub coord: Coord,
+    /// maximum distance (in meters) of the places to the coord
+    pub radius: f64,
+    pub limit: u64,
+    pub types: &'a [&'a str],
+    pub pt_datasets: &'a [&'a str],
+    pub poi_datasets: &'a [&'a str],
+}
+
+impl ReverseQuery<'static> {
+    /// the query of the nearest address (or street) within 1km of the coord
+    pub fn address(coord: Coord) -> Self {
+        ReverseQuery {
+            coord,
+            radius: DEFAULT_REVERSE_RADIUS,
+            limit: 1,
+            types: ADDRESS_TYPES,
+            pt_datasets: &[],
+            poi_datasets: &[],
+        }
+    }
+}
+
+/// All the parameters of a structured geocoding, each part of the address
+/// being matched against its own field
+#[derive(Debug, Clone, Default)]
+pub struct StructuredQuery<'a> {
+    pub house_number: Option<&'a str>,
+    pub street: Option<&'a str>,
+    pub postcode: Option<&'a str>,
+    pub city: Option<&'a str>,
+    pub country: Option<&'a str>,
+    pub offset: u64,
+    pub limit: u64,
+    pub langs: &'a [&'a str],
+    /// the relevance constants, the default ones if none
+    pub profile: Option<&'a RankingProfile>,
+}
+
+impl<'a> StructuredQuery<'a> {
+    /// the relevance constants of the search
+    pub fn profile(&self) -> &'a RankingProfile {
+        match self.profile {
+            Some(profile) => profile,
+            None => ranking::default_profile(),
+        }
+    }
+
+    /// the kind of the searched places, given by the most precise part of the query:
+    /// the addresses if there is a house number, the streets if there is a street,
+    /// the admins otherwise
+    pub fn kind(&self) -> PlaceKind {
+        if self.house_number.is_some() {
+            PlaceKind::Addr
+        } else if self.street.is_some() {
+            PlaceKind::Street
+        } else {
+            PlaceKind::Admin
+        }
+    }
+}
+
+/// Read access to the places, this is all bragi needs
+pub trait PlaceSearch {
+    /// get the places of the query next to its coord, the nearest first
+    fn reverse(&mut self, query: &ReverseQuery<'_>) -> Result<Vec<Place>, Error>;
+
+    /// get the addresses (or streets) next to the coord
+    fn get_address(&mut self, coord: &Coord) -> Result<Vec<Place>, Error> {
+        self.reverse(&ReverseQuery::address(*coord))
+    }
+
+    /// get the addresses (or streets) next to each coord
+    /// (the i-th result is the one of the i-th coord, None if its search has failed)
+    fn get_addresses(&mut self, coords: &[Coord]) -> Vec<Option<Vec<Place>>> {
+        coords
+            .iter()
+            .map(|coord| {
+                self.get_address(coord)
+                    .map_err(|e| warn!("impossible to get the address of {:?}: {}", coord, e))
+                    .ok()
+            })
+            .collect()
+    }
+
+    /// search the places matching the query
+    fn search(
+        &mut self,
+        query: &SearchQuery<'_>,
+        match_type: MatchType,
+    ) -> Result<Vec<Place>, Error>;
+
+    /// search the places matching each part of the structured query
+    fn search_structured(&mut self, query: &StructuredQuery<'_>) -> Result<Vec<Place>, Error>;
+
+    /// get the places with the given id
+    fn features(
+        &mut self,
+        pt_datasets: &[&str],
+        poi_datasets: &[&str],
+        all_data: bool,
+        id: &str,
+    ) -> Result<Vec<Place>, Error>;
+}
+
+/// Storage of the places, used by the importers to build and publish indexes
+///
+/// `Rubber` is the elasticsearch implementation.
+pub trait PlaceStore: PlaceSearch {
+    /// create a new index for this doc_type and this dataset
+    fn make_index<T: MimirObject>(
+        &mut self,
+        dataset: &str,
+        index_settings: &IndexSettings,
+    ) -> Result<TypedIndex<T>, Error>;
+
+    /// publish the index as the new index for this doc_type and this dataset
+    fn publish_index<T: MimirObject>(
+        &mut self,
+        dataset: &str,
+        index: TypedIndex<T>,
+        visibility: IndexVisibility,
+        index_settings: &IndexSettings,
+    ) -> Result<(), Error>;
+
+    /// add all the elements of 'iter' in the index
+    fn bulk_index<T, I>(&mut self, index: &TypedIndex<T>, iter: I) -> Result<usize, Error>
+    where
+        T: MimirObject + Send + 'static,
+        I: Iterator<Item = T>;
+
+    /// get all the objects of an index (or of an alias)
+    fn get_all_objects_from_index<T>(&mut self, index: &str) -> Result<Vec<T>, Error>
+    where
+        for<'de> T: MimirObject + serde::de::Deserialize<'de> + std::fmt::Debug;
+
+    fn get_all_admins(&mut self) -> Result<Vec<Admin>, Error> {
+        self.get_all_objects_from_index(&get_main_type_index::<Admin>())
+    }
+
+    /// add all the elements of 'iter' in a new index and publish it
+    fn index<T, I>(
+        &mut self,
+        dataset: &str,
+        visibility: IndexVisibility,
+        index_settings: &IndexSettings,
+        iter: I,
+    ) -> Result<usize, Error>
+    where
+        T: MimirObject + Send + 'static,
+        I: Iterator<Item = T>,
+    {
+        let index = self.make_index(dataset, index_settings)?;
+        let nb_elements = self.bulk_index(&index, iter)?;
+        self.publish_index(dataset, index, visibility, index_settings)?;
+        Ok(nb_elements)
+    }
+
+    /// update the published index of this doc_type/dataset with the elements of 'iter'
+    ///
+    /// By default the whole index is rebuilt.
+    fn incremental_index<T, I>(
+        &mut self,
+        dataset: &str,
+        visibility: IndexVisibility,
+        index_settings: &IndexSettings,
+        iter: I,
+    ) -> Result<IndexDelta, Error>
+    where
+        T: MimirObject + Send + 'static,
+        I: Iterator<Item = T>,
+    {
+        let nb_upserted = self.index(dataset, visibility, index_settings, iter)?;
+        Ok(IndexDelta {
+            nb_upserted,
+            ..Default::default()
+        })
+    }
+}
@@ -0,0 +1,105 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+//! Synonyms of the `synonym_filter` of the elasticsearch indexes.
+//!
+//! The synonyms are given in the Solr format: one rule by line, either a list of
+//! equivalent words (`st,saint`) or an explicit mapping (`bd,bld => boulevard`).
+//! Empty lines and lines starting with `#` are ignored.
+
+use failure::{bail, Error, ResultExt};
+use std::path::Path;
+
+/// default synonyms, for French data
+pub const SYNONYMS: [&'static str; 17] = [
+    "cc,centre commercial",
+    "hotel de ville,mairie",
+    "gare sncf,gare",
+    "chu,chr,hopital",
+    "ld,lieu-dit",
+    "st,saint",
+    "ste,sainte",
+    "bvd,bld,bd,boulevard",
+    "pt,pont",
+    "rle,ruelle",
+    "rte,route",
+    "vla,villa",
+    "grand-champ,grandchamp",
+    "fac,faculte,ufr,universite",
+    "embarcadere,gare maritime",
+    "cpam,securite sociale",
+    "anpe,pole emploi",
+];
+
+pub fn default_synonyms() -> Vec<String> {
+    SYNONYMS.iter().map(|s| s.to_string()).collect()
+}
+
+/// read a synonyms file
+pub fn read_synonyms(path: &Path) -> Result<Vec<String>, Error> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|_| format!("impossible to read synonyms file {:?}", path))?;
+    Ok(parse_synonyms(&content).with_context(|_| format!("invalid synonyms file {:?}", path))?)
+}
+
+pub fn parse_synonyms(content: &str) -> Result<Vec<String>, Error> {
+    let mut synonyms = vec![];
+    for (i, line) in content.lines().enumerate() {
+        let rule = line.trim();
+        if rule.is_empty() || rule.starts_with('#') {
+            continue;
+        }
+        let is_valid = |words: &str| words.split(',').all(|w| !w.trim().is_empty());
+        let is_valid_rule = match rule.find("=>") {
+            Some(pos) => is_valid(&rule[..pos]) && is_valid(&rule[pos + 2..]),
+            None => is_valid(rule),
+        };
+        if !is_valid_rule {
+            bail!("invalid synonyms rule line {}: {}", i + 1, rule);
+        }
+        synonyms.push(rule.to_string());
+    }
+    Ok(synonyms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_synonyms() {
+        let synonyms =
+            parse_synonyms("# synonyms for Spain\n\nc,calle\n  av, avda => avenida  \n").unwrap();
+        assert_eq!(synonyms, vec!["c,calle", "av, avda => avenida"]);
+
+        assert!(parse_synonyms("c,,calle").is_err());
+        assert!(parse_synonyms("av =>").is_err());
+    }
+}
@@ -0,0 +1,132 @@
+//! Relevance regression check of the autocomplete.
+//!
+//! Runs the queries of a file on an in-process bragi, prints their precision@k and
+//! reciprocal rank, and compares them with a baseline. The exit code is not 0 when
+//! an expected place is not at its acceptable rank, or when the results are worse
+//! than the baseline.
+
+use slog_scope::{error, info};
+use std::path::PathBuf;
+use structopt::StructOpt;
+use tools::relevance::{evaluate, read_queries, Report};
+use tools::BragiHandler;
+
+#[derive(Debug, StructOpt)]
+struct Args {
+    /// Json lines file of the queries, with their expected places.
+    #[structopt(long = "queries", parse(from_os_str))]
+    queries: PathBuf,
+    /// Number of results evaluated for each query.
+    #[structopt(long = "nb-results", default_value = "5")]
+    k: usize,
+    /// Report of a previous run, to compare the results with.
+    #[structopt(long = "baseline", parse(from_os_str))]
+    baseline: Option<PathBuf>,
+    /// Write the report of this run, to use it as the next baseline.
+    #[structopt(long = "output", parse(from_os_str))]
+    output: Option<PathBuf>,
+    /// Accepted decrease of the mean precision@k and of the MRR.
+    #[structopt(long = "tolerance", default_value = "0")]
+    tolerance: f64,
+    #[structopt(flatten)]
+    bragi: bragi::Args,
+}
+
+fn run(args: Args) -> Result<bool, failure::Error> {
+    let queries = read_queries(&args.queries)?;
+    info!("{} queries read from {:?}", queries.len(), args.queries);
+    let mut bragi = BragiHandler::from_args(args.bragi);
+    let report = evaluate(&mut bragi, &queries, args.k);
+
+    for query in &report.queries {
+        println!(
+            "{:.2}\t{:.2}\t{}",
+            query.precision, query.reciprocal_rank, query.key
+        );
+    }
+    println!(
+        "precision@{}: {:.4}, MRR: {:.4} ({} queries)",
+        report.k,
+        report.precision,
+        report.mrr,
+        report.queries.len()
+    );
+
+    let mut success = true;
+    for failure in report.failures() {
+        success = false;
+        println!(
+            "FAILED {}: {:?} not at their rank in {:?}",
+            failure.key, failure.misplaced, failure.ids
+        );
+    }
+
+    if let Some(ref path) = args.baseline {
+        let baseline = Report::read(path)?;
+        if baseline.k != report.k {
+            failure::bail!(
+                "the baseline has been computed with {} results, not {}",
+                baseline.k,
+                report.k
+            );
+        }
+        let comparison = report.compare(&baseline, args.tolerance);
+        for diff in &comparison.diffs {
+            let status = if diff.is_worse() { "WORSE" } else { "CHANGED" };
+            println!("{} {}", status, diff.current.key);
+            println!(
+                "\tbaseline: {:.2} {:.2} {:?}",
+                diff.baseline.precision, diff.baseline.reciprocal_rank, diff.baseline.ids
+            );
+            println!(
+                "\tcurrent:  {:.2} {:.2} {:?}",
+                diff.current.precision, diff.current.reciprocal_rank, diff.current.ids
+            );
+        }
+        for query in &comparison.added {
+            println!("NEW {}", query.key);
+        }
+        for query in &comparison.removed {
+            println!("REMOVED {}", query.key);
+        }
+        println!(
+            "baseline precision@{}: {:.4}, MRR: {:.4}",
+            baseline.k, baseline.precision, baseline.mrr
+        );
+        println!(
+            "on the {} queries of the baseline still run: precision@{} {:+.4}, MRR {:+.4}",
+            baseline.queries.len() - comparison.removed.len(),
+            report.k,
+            comparison.precision_delta,
+            comparison.mrr_delta
+        );
+        if comparison.regressed {
+            println!("REGRESSION: the results are worse than the baseline");
+            success = false;
+        }
+    }
+
+    if let Some(ref path) = args.output {
+        report.write(path)?;
+        info!("report written in {:?}", path);
+    }
+    Ok(success)
+}
+
+// the exit code, computed in another method to std::exit after
+// the destruction of the logger (so we won't loose any messages)
+fn wrapped_main() -> i32 {
+    let _guard = mimir::logger_init();
+    match run(Args::from_args()) {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(e) => {
+            error!("relevance check failed: {}", e);
+            2
+        }
+    }
+}
+
+fn main() {
+    std::process::exit(wrapped_main());
+}
@@ -4,6 +4,8 @@ use serde_json::Map;
 use slog_scope::info;
 use std::time::Duration;
 
+pub mod relevance;
+
 pub struct ElasticSearchWrapper<'a> {
     pub docker_wrapper: &'a DockerWrapper,
     pub rubber: mimir::rubber::Rubber,
@@ -152,7 +154,8 @@ impl<'a> ElasticSearchWrapper<'a> {
                     doc_type.and_then(|doc_type| {
                         // The real object is contained in the _source section.
                         obj.get("_source").and_then(|src| {
-                            bragi::query::make_place(doc_type, Some(Box::new(src.clone())))
+                            let doc_type = mimir::rubber::get_doc_type(doc_type, Some(src));
+                            mimir::rubber::make_place(doc_type, Some(Box::new(src.clone())), None)
                         })
                     })
                 })
@@ -167,10 +170,22 @@ pub struct BragiHandler {
 
 impl BragiHandler {
     pub fn new(url: String) -> BragiHandler {
-        let ctx = bragi::Context::from(&bragi::Args {
-            connection_string: url.clone(),
+        Self::from_args(bragi::Args {
+            connection_string: url,
             ..Default::default()
-        });
+        })
+    }
+
+    /// bragi serving an embedded index instead of elasticsearch
+    pub fn new_embedded(dir: &std::path::Path) -> BragiHandler {
+        Self::from_args(bragi::Args {
+            embedded_index: Some(dir.to_path_buf()),
+            ..Default::default()
+        })
+    }
+
+    pub fn from_args(args: bragi::Args) -> BragiHandler {
+        let ctx = bragi::Context::from(&args);
 
         let prometheus = bragi::prometheus_middleware::PrometheusMetrics::new("bragi", "/metrics");
         let srv = actix_http_test::TestServer::new(move || {
@@ -236,6 +251,16 @@ impl BragiHandler {
         &mut self,
         q: &str,
         shape: &'static str,
+    ) -> (actix_http::http::StatusCode, bytes::Bytes) {
+        self.raw_post_body(q, "application/json", shape.to_string())
+    }
+
+    /// post the body with its content type
+    pub fn raw_post_body(
+        &mut self,
+        q: &str,
+        content_type: &str,
+        body: String,
     ) -> (actix_http::http::StatusCode, bytes::Bytes) {
         let q = url_encode(q);
         let mut r = self
@@ -243,8 +268,8 @@ impl BragiHandler {
             .block_on(
                 self.app
                     .post(q)
-                    .header(actix_web::http::header::CONTENT_TYPE, "application/json")
-                    .send_body(shape),
+                    .header(actix_web::http::header::CONTENT_TYPE, content_type)
+                    .send_body(body),
             )
             .unwrap_or_else(|e| panic!("impossible to query bragi: {}", e));
 
@@ -268,6 +293,18 @@ impl BragiHandler {
         self.to_json(s)
     }
 
+    /// post a batch, and read the json lines of its results
+    pub fn post_batch(&mut self, q: &str, content_type: &str, body: String) -> Vec<Value> {
+        let (status, s) = self.raw_post_body(q, content_type, body);
+        assert!(status.is_success(), "invalid status: {}", status);
+
+        std::str::from_utf8(&s)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
     pub fn to_json(&mut self, b: bytes::Bytes) -> Value {
         let body = std::str::from_utf8(&b).unwrap();
         serde_json::from_str(body).unwrap()
@@ -0,0 +1,422 @@
+//! Relevance evaluation of the autocomplete.
+//!
+//! The queries are read from a json lines file, one query by line, with the ids of the
+//! places expected in its results, and optionally the worst acceptable rank of each of them:
+//!
+//! ```json
+//! {"q": "Melun", "expected": [{"id": "admin:osm:relation:80071", "rank": 1}]}
+//! {"q": "rue de la reine blanche", "params": "type[]=street", "expected": [{"id": "street:1"}]}
+//! ```
+//!
+//! Each query is evaluated on its first `k` results:
+//! - the precision@k is the share of these `k` results that are expected places
+//!   (so a query with a single expected place scores at most `1 / k`),
+//! - the reciprocal rank is `1 / rank` of the first expected place found (0 if none).
+//!
+//! The report of a run can be saved as a baseline, to find the queries whose results
+//! became worse after a ranking change.
+
+use crate::BragiHandler;
+use failure::{Error, ResultExt};
+use serde::{Deserialize, Serialize};
+use slog_scope::warn;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter};
+use std::path::Path;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ExpectedPlace {
+    pub id: String,
+    /// worst acceptable rank (starting at 1) of the place, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rank: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TestQuery {
+    pub q: String,
+    /// other parameters of the autocomplete, like `type[]=poi&lat=48.5&lon=2.6`
+    #[serde(default)]
+    pub params: String,
+    pub expected: Vec<ExpectedPlace>,
+}
+
+impl TestQuery {
+    /// identifier of the query, to find it in the baseline
+    pub fn key(&self) -> String {
+        if self.params.is_empty() {
+            self.q.clone()
+        } else {
+            format!("{} ({})", self.q, self.params)
+        }
+    }
+
+    fn url(&self, k: usize) -> String {
+        let q: String = url::form_urlencoded::byte_serialize(self.q.as_bytes()).collect();
+        let mut url = format!("/autocomplete?q={}&limit={}", q, k);
+        if !self.params.is_empty() {
+            url.push('&');
+            url.push_str(self.params.trim_start_matches('&'));
+        }
+        url
+    }
+}
+
+/// read the queries of a json lines file (the empty lines and the lines starting with `#` are ignored)
+pub fn read_queries(path: &Path) -> Result<Vec<TestQuery>, Error> {
+    let file = File::open(path).with_context(|_| format!("impossible to open {:?}", path))?;
+    let mut queries = vec![];
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let query = serde_json::from_str(line)
+            .with_context(|_| format!("invalid query at line {} of {:?}", i + 1, path))?;
+        queries.push(query);
+    }
+    Ok(queries)
+}
+
+/// The evaluation of the results of a query
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct QueryResult {
+    pub key: String,
+    /// ids of the first k results
+    pub ids: Vec<String>,
+    pub precision: f64,
+    pub reciprocal_rank: f64,
+    /// the expected places not found at their acceptable rank
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub misplaced: Vec<String>,
+}
+
+impl QueryResult {
+    pub fn evaluate(query: &TestQuery, ids: Vec<String>, k: usize) -> QueryResult {
+        let rank_of = |id: &str| ids.iter().take(k).position(|i| i == id).map(|p| p + 1);
+        let ranks: Vec<_> = query.expected.iter().map(|e| rank_of(&e.id)).collect();
+
+        let nb_found = ranks.iter().filter(|r| r.is_some()).count();
+        let precision = if k == 0 || query.expected.is_empty() {
+            1.
+        } else {
+            nb_found as f64 / k as f64
+        };
+        let reciprocal_rank = ranks
+            .iter()
+            .filter_map(|r| *r)
+            .min()
+            .map_or(0., |r| 1. / r as f64);
+        let misplaced = query
+            .expected
+            .iter()
+            .zip(&ranks)
+            .filter(|(e, r)| match (e.rank, r) {
+                (Some(max_rank), Some(r)) => *r > max_rank,
+                (Some(_), None) => true,
+                (None, _) => false,
+            })
+            .map(|(e, _)| e.id.clone())
+            .collect();
+
+        QueryResult {
+            key: query.key(),
+            ids: ids.into_iter().take(k).collect(),
+            precision,
+            reciprocal_rank,
+            misplaced,
+        }
+    }
+}
+
+/// The evaluation of all the queries
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Report {
+    pub k: usize,
+    /// mean precision@k
+    pub precision: f64,
+    /// mean reciprocal rank
+    pub mrr: f64,
+    pub queries: Vec<QueryResult>,
+}
+
+impl Report {
+    pub fn new(k: usize, queries: Vec<QueryResult>) -> Report {
+        let mean = |f: fn(&QueryResult) -> f64| {
+            if queries.is_empty() {
+                0.
+            } else {
+                queries.iter().map(f).sum::<f64>() / queries.len() as f64
+            }
+        };
+        Report {
+            k,
+            precision: mean(|r| r.precision),
+            mrr: mean(|r| r.reciprocal_rank),
+            queries,
+        }
+    }
+
+    pub fn read(path: &Path) -> Result<Report, Error> {
+        let file = File::open(path).with_context(|_| format!("impossible to open {:?}", path))?;
+        Ok(serde_json::from_reader(BufReader::new(file))
+            .with_context(|_| format!("invalid report {:?}", path))?)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), Error> {
+        let file =
+            File::create(path).with_context(|_| format!("impossible to create {:?}", path))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    /// the queries with an expected place not found at its acceptable rank
+    pub fn failures(&self) -> impl Iterator<Item = &QueryResult> {
+        self.queries.iter().filter(|q| !q.misplaced.is_empty())
+    }
+
+    /// compare the report with a baseline
+    ///
+    /// The metrics are compared on the queries of both reports (the added and removed
+    /// queries are listed apart): they regress if their mean is lower than the one of
+    /// the baseline by more than `tolerance`.
+    pub fn compare<'a>(&'a self, baseline: &'a Report, tolerance: f64) -> Comparison<'a> {
+        let baseline_queries: BTreeMap<_, _> =
+            baseline.queries.iter().map(|q| (&q.key, q)).collect();
+        let current_keys: BTreeSet<_> = self.queries.iter().map(|q| &q.key).collect();
+
+        let mut common = vec![];
+        let mut added = vec![];
+        for current in &self.queries {
+            match baseline_queries.get(&current.key) {
+                Some(&baseline) => common.push(QueryDiff { baseline, current }),
+                None => added.push(current),
+            }
+        }
+        let removed = baseline
+            .queries
+            .iter()
+            .filter(|q| !current_keys.contains(&q.key))
+            .collect();
+
+        let mean_delta = |f: fn(&QueryResult) -> f64| {
+            if common.is_empty() {
+                0.
+            } else {
+                common
+                    .iter()
+                    .map(|d| f(d.current) - f(d.baseline))
+                    .sum::<f64>()
+                    / common.len() as f64
+            }
+        };
+        let precision_delta = mean_delta(|r| r.precision);
+        let mrr_delta = mean_delta(|r| r.reciprocal_rank);
+        Comparison {
+            diffs: common.into_iter().filter(QueryDiff::is_changed).collect(),
+            added,
+            removed,
+            precision_delta,
+            mrr_delta,
+            regressed: precision_delta < -tolerance || mrr_delta < -tolerance,
+        }
+    }
+}
+
+/// A query of the baseline whose results changed
+#[derive(Debug)]
+pub struct QueryDiff<'a> {
+    pub baseline: &'a QueryResult,
+    pub current: &'a QueryResult,
+}
+
+impl<'a> QueryDiff<'a> {
+    fn is_changed(&self) -> bool {
+        self.baseline.ids != self.current.ids
+            || self.baseline.precision != self.current.precision
+            || self.baseline.reciprocal_rank != self.current.reciprocal_rank
+    }
+
+    /// the results of the query became worse
+    pub fn is_worse(&self) -> bool {
+        self.current.precision < self.baseline.precision
+            || self.current.reciprocal_rank < self.baseline.reciprocal_rank
+    }
+}
+
+#[derive(Debug)]
+pub struct Comparison<'a> {
+    /// the queries of both reports whose results changed
+    pub diffs: Vec<QueryDiff<'a>>,
+    /// the queries not in the baseline
+    pub added: Vec<&'a QueryResult>,
+    /// the queries of the baseline not in the report
+    pub removed: Vec<&'a QueryResult>,
+    /// change of the mean precision@k, on the queries of both reports
+    pub precision_delta: f64,
+    /// change of the MRR, on the queries of both reports
+    pub mrr_delta: f64,
+    /// the mean metrics are worse than the ones of the baseline
+    pub regressed: bool,
+}
+
+/// run the queries on bragi and evaluate their first `k` results
+pub fn evaluate(bragi: &mut BragiHandler, queries: &[TestQuery], k: usize) -> Report {
+    let results = queries
+        .iter()
+        .map(|query| {
+            let (status, response) = bragi.get_unchecked_json(&query.url(k));
+            let ids = if status.is_success() {
+                response
+                    .pointer("/features")
+                    .and_then(|f| f.as_array())
+                    .map(|features| {
+                        features
+                            .iter()
+                            .filter_map(|f| f.pointer("/properties/geocoding/id"))
+                            .filter_map(|id| id.as_str())
+                            .map(str::to_string)
+                            .collect()
+                    })
+                    .unwrap_or_else(Vec::new)
+            } else {
+                warn!("query {} failed: {} {}", query.key(), status, response);
+                vec![]
+            };
+            QueryResult::evaluate(query, ids, k)
+        })
+        .collect();
+    Report::new(k, results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(expected: &[(&str, Option<usize>)]) -> TestQuery {
+        TestQuery {
+            q: "melun".to_string(),
+            params: String::new(),
+            expected: expected
+                .iter()
+                .map(|(id, rank)| ExpectedPlace {
+                    id: id.to_string(),
+                    rank: *rank,
+                })
+                .collect(),
+        }
+    }
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn test_evaluate_query() {
+        let q = query(&[("a", Some(1)), ("b", Some(2))]);
+        let res = QueryResult::evaluate(&q, ids(&["c", "b", "d", "e", "f", "a"]), 5);
+        assert_eq!(res.ids, ids(&["c", "b", "d", "e", "f"]));
+        assert_eq!(res.precision, 0.2);
+        assert_eq!(res.reciprocal_rank, 0.5);
+        assert_eq!(res.misplaced, ids(&["a"]));
+
+        // only one place is expected, the other results lower the precision
+        let q = query(&[("a", None)]);
+        let res = QueryResult::evaluate(&q, ids(&["c", "b", "a"]), 5);
+        assert_eq!(res.precision, 0.2);
+        assert!((res.reciprocal_rank - 1. / 3.).abs() < 1e-9);
+        assert!(res.misplaced.is_empty());
+        let res = QueryResult::evaluate(&q, ids(&["a"]), 1);
+        assert_eq!(res.precision, 1.);
+
+        let res = QueryResult::evaluate(&q, vec![], 5);
+        assert_eq!(res.precision, 0.);
+        assert_eq!(res.reciprocal_rank, 0.);
+    }
+
+    #[test]
+    fn test_compare_reports() {
+        let q = query(&[("a", None)]);
+        let baseline = Report::new(5, vec![QueryResult::evaluate(&q, ids(&["a", "b"]), 5)]);
+        assert_eq!(baseline.mrr, 1.);
+
+        let same = baseline.clone();
+        let comparison = same.compare(&baseline, 0.);
+        assert!(comparison.diffs.is_empty());
+        assert!(!comparison.regressed);
+
+        let worse = Report::new(5, vec![QueryResult::evaluate(&q, ids(&["b", "a"]), 5)]);
+        assert_eq!(worse.mrr, 0.5);
+        let comparison = worse.compare(&baseline, 0.);
+        assert_eq!(comparison.diffs.len(), 1);
+        assert!(comparison.diffs[0].is_worse());
+        assert!(comparison.regressed);
+        // the regression is accepted with a large enough tolerance
+        assert!(!worse.compare(&baseline, 0.5).regressed);
+
+        // the improvements are not regressions
+        let comparison = baseline.compare(&worse, 0.);
+        assert!(!comparison.diffs[0].is_worse());
+        assert!(!comparison.regressed);
+    }
+
+    #[test]
+    fn test_compare_reports_with_other_queries() {
+        let q = query(&[("a", None)]);
+        let mut not_found = query(&[("z", None)]);
+        not_found.q = "nowhere".to_string();
+        let baseline = Report::new(5, vec![QueryResult::evaluate(&q, ids(&["a"]), 5)]);
+
+        // a new query without result lowers the mean metrics, but is not a regression
+        let report = Report::new(
+            5,
+            vec![
+                QueryResult::evaluate(&q, ids(&["a"]), 5),
+                QueryResult::evaluate(&not_found, vec![], 5),
+            ],
+        );
+        assert_eq!(report.mrr, 0.5);
+        let comparison = report.compare(&baseline, 0.);
+        assert!(!comparison.regressed);
+        assert!(comparison.diffs.is_empty());
+        assert_eq!(comparison.mrr_delta, 0.);
+        assert_eq!(
+            comparison.added.iter().map(|q| &q.key).collect::<Vec<_>>(),
+            vec!["nowhere"]
+        );
+        assert!(comparison.removed.is_empty());
+
+        // the queries of the baseline not run anymore are listed
+        let mut other = query(&[("b", None)]);
+        other.q = "other".to_string();
+        let report = Report::new(5, vec![QueryResult::evaluate(&other, ids(&["b"]), 5)]);
+        let comparison = report.compare(&baseline, 0.);
+        assert!(!comparison.regressed);
+        assert_eq!(
+            comparison.added.iter().map(|q| &q.key).collect::<Vec<_>>(),
+            vec!["other"]
+        );
+        assert_eq!(
+            comparison
+                .removed
+                .iter()
+                .map(|q| &q.key)
+                .collect::<Vec<_>>(),
+            vec!["melun"]
+        );
+    }
+
+    #[test]
+    fn test_url() {
+        let mut q = query(&[]);
+        q.q = "2 rue & co".to_string();
+        q.params = "type[]=house".to_string();
+        assert_eq!(
+            q.url(5),
+            "/autocomplete?q=2+rue+%26+co&limit=5&type[]=house"
+        );
+        assert_eq!(q.key(), "2 rue & co (type[]=house)");
+    }
+}
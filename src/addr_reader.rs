@@ -1,7 +1,8 @@
 use crate::Error;
 use csv;
 use failure::ResultExt;
-use mimir::rubber::{IndexSettings, IndexVisibility, Rubber};
+use mimir::rubber::{IndexSettings, IndexVisibility};
+use mimir::store::PlaceStore;
 use mimir::Addr;
 use par_map::ParMap;
 use serde::de::DeserializeOwned;
@@ -9,27 +10,22 @@ use slog_scope::{debug, error, info};
 use std::marker::{Send, Sync};
 use std::path::PathBuf;
 
-pub fn import_addresses<T, F>(
-    rubber: &mut Rubber,
+/// Read the addresses of the files, converted by `into_addr` on `nb_threads` threads
+///
+/// The unreadable files and lines, and the addresses without street name, are skipped.
+pub fn read_addresses<T, F>(
     has_headers: bool,
     nb_threads: usize,
-    index_settings: IndexSettings,
-    dataset: &str,
     files: impl IntoIterator<Item = PathBuf>,
     into_addr: F,
-) -> Result<(), Error>
+) -> impl Iterator<Item = Addr>
 where
     F: Fn(T) -> Addr + Send + Sync + 'static,
     T: DeserializeOwned + Send + 'static,
 {
-    let addr_index = rubber
-        .make_index(dataset, &index_settings)
-        .with_context(|_| format!("Error occurred when making index {}", dataset))?;
-    info!("Add data in elasticsearch db.");
-
-    let iter = files
+    files
         .into_iter()
-        .flat_map(|f| {
+        .flat_map(move |f| {
             info!("importing {:?}...", &f);
             csv::ReaderBuilder::new()
                 .has_headers(has_headers)
@@ -51,14 +47,51 @@ where
                 debug!("Address {} has no street name and has been ignored.", a.id);
                 false
             }
-        });
-    let nb = rubber
-        .bulk_index(&addr_index, iter)
+        })
+}
+
+/// Import the addresses into the store (elasticsearch or an embedded index)
+///
+/// If `incremental` is set, only the addresses that changed are sent to a copy
+/// of the published index, instead of rebuilding the index from scratch.
+pub fn import_addresses<S>(
+    store: &mut S,
+    incremental: bool,
+    index_settings: IndexSettings,
+    dataset: &str,
+    addresses: impl Iterator<Item = Addr>,
+) -> Result<(), Error>
+where
+    S: PlaceStore,
+{
+    info!("Add data in the store.");
+
+    if incremental {
+        let delta = store
+            .incremental_index(dataset, IndexVisibility::Public, &index_settings, addresses)
+            .with_context(|_| format!("Error occurred when updating index {}", dataset))?;
+        info!(
+            "importing addresses: {} addresses added or modified, {} unchanged, {} removed, {} failed.",
+            delta.nb_upserted, delta.nb_unchanged, delta.nb_deleted, delta.nb_failed
+        );
+        return Ok(());
+    }
+
+    let addr_index = store
+        .make_index(dataset, &index_settings)
+        .with_context(|_| format!("Error occurred when making index {}", dataset))?;
+    let nb = store
+        .bulk_index(&addr_index, addresses)
         .with_context(|_| format!("failed to bulk insert"))?;
     info!("importing addresses: {} addresses added.", nb);
 
-    rubber
-        .publish_index(dataset, addr_index, IndexVisibility::Public)
+    store
+        .publish_index(
+            dataset,
+            addr_index,
+            IndexVisibility::Public,
+            &index_settings,
+        )
         .context("Error while publishing the index")?;
     Ok(())
 }
@@ -29,11 +29,16 @@
 // www.navitia.io
 
 use lazy_static::lazy_static;
+use mimir::embedded::EmbeddedStore;
 use mimir::objects::Admin;
-use mimir::rubber::{IndexSettings, Rubber};
-use mimirsbrunn::addr_reader::import_addresses;
+use mimir::rubber::IndexSettings;
+use mimir::store::PlaceStore;
+use mimirsbrunn::addr_reader::{import_addresses, read_addresses};
 use mimirsbrunn::admin_geofinder::AdminGeoFinder;
 use mimirsbrunn::labels;
+use mimirsbrunn::utils::{
+    import_info, read_synonyms, BulkArgs, ConnectionArgs, PublicationChecksArgs,
+};
 use serde::{Deserialize, Serialize};
 use slog_scope::{info, warn};
 use std::collections::BTreeMap;
@@ -177,22 +182,20 @@ fn build_admin_from_bano_city(city: &str) -> Admin {
     }
 }
 
-fn index_bano<I>(
-    cnx_string: &str,
+fn index_bano<S, I>(
+    store: &mut S,
     dataset: &str,
     files: I,
     nb_threads: usize,
-    nb_shards: usize,
-    nb_replicas: usize,
+    index_settings: IndexSettings,
     use_old_index_format: bool,
+    incremental: bool,
 ) -> Result<(), mimirsbrunn::Error>
 where
+    S: PlaceStore,
     I: Iterator<Item = std::path::PathBuf>,
 {
-    let mut rubber = Rubber::new(cnx_string);
-    rubber.initialize_templates()?;
-
-    let admins = rubber.get_all_admins().unwrap_or_else(|err| {
+    let admins = store.get_all_admins().unwrap_or_else(|err| {
         warn!(
             "Administratives regions not found in es db for dataset {}. (error: {})",
             dataset, err
@@ -209,20 +212,10 @@ where
         })
         .collect();
 
-    let index_settings = IndexSettings {
-        nb_shards: nb_shards,
-        nb_replicas: nb_replicas,
-    };
-
-    import_addresses(
-        &mut rubber,
-        false,
-        nb_threads,
-        index_settings,
-        dataset,
-        files,
-        move |b: Bano| b.into_addr(&admins_by_insee, &admins_geofinder, use_old_index_format),
-    )
+    let addresses = read_addresses(false, nb_threads, files, move |b: Bano| {
+        b.into_addr(&admins_by_insee, &admins_geofinder, use_old_index_format)
+    });
+    import_addresses(store, incremental, index_settings, dataset, addresses)
 }
 
 #[derive(StructOpt, Debug)]
@@ -253,37 +246,81 @@ struct Args {
     /// Number of replicas for the es index
     #[structopt(short = "r", long = "nb-replicas", default_value = "1")]
     nb_replicas: usize,
+    /// Number of previous indexes kept (without any alias) after the import,
+    /// to be able to rollback to them with mimir_rollback
+    #[structopt(long = "nb-retained-indexes", default_value = "0")]
+    nb_retained_indexes: usize,
+    /// Synonyms file, in the Solr format (one rule by line, like `st,saint`).
+    /// By default a list of French synonyms is used.
+    #[structopt(long = "synonyms", parse(from_os_str))]
+    synonyms: Option<PathBuf>,
+    #[structopt(flatten)]
+    publication_checks: PublicationChecksArgs,
+    #[structopt(flatten)]
+    bulk: BulkArgs,
+    #[structopt(flatten)]
+    connection: ConnectionArgs,
     /// If set to true, the number inside the address won't be used for the index generation,
     /// therefore, different addresses with the same position will disappear.
     #[structopt(long = "use-old-index-format")]
     use_old_index_format: bool,
+    /// If set to true, only the addresses that changed are sent to a copy of the
+    /// published index, instead of sending all of them to a new index.
+    #[structopt(long = "incremental")]
+    incremental: bool,
+    /// Directory of an embedded index to import the addresses into,
+    /// instead of elasticsearch.
+    #[structopt(long = "embedded-index", parse(from_os_str))]
+    embedded_index: Option<PathBuf>,
 }
 
-fn run(args: Args) -> Result<(), mimirsbrunn::Error> {
-    info!("importing bano into Mimir");
+fn import_bano<S: PlaceStore>(store: &mut S, args: Args) -> Result<(), mimirsbrunn::Error> {
+    let index_settings = IndexSettings {
+        nb_shards: args.nb_shards,
+        nb_replicas: args.nb_replicas,
+        nb_retained_indexes: args.nb_retained_indexes,
+        publication_checks: (&args.publication_checks).into(),
+        synonyms: read_synonyms(args.synonyms.as_ref())?,
+    };
     if args.input.is_dir() {
         let paths: std::fs::ReadDir = fs::read_dir(&args.input)?;
         index_bano(
-            &args.connection_string,
+            store,
             &args.dataset,
             paths.map(|p| p.unwrap().path()),
             args.nb_threads,
-            args.nb_shards,
-            args.nb_replicas,
+            index_settings,
             args.use_old_index_format,
+            args.incremental,
         )
     } else {
         index_bano(
-            &args.connection_string,
+            store,
             &args.dataset,
             std::iter::once(args.input),
             args.nb_threads,
-            args.nb_shards,
-            args.nb_replicas,
+            index_settings,
             args.use_old_index_format,
+            args.incremental,
         )
     }
 }
+
+fn run(args: Args) -> Result<(), mimirsbrunn::Error> {
+    info!("importing bano into Mimir");
+    match args.embedded_index.clone() {
+        Some(dir) => import_bano(&mut EmbeddedStore::open(dir)?, args),
+        None => {
+            let mut rubber = args
+                .connection
+                .rubber(&args.connection_string)?
+                .with_bulk_settings((&args.bulk).into())
+                .with_import_info(import_info(&[&args.input])?);
+            rubber.initialize_templates()?;
+            import_bano(&mut rubber, args)
+        }
+    }
+}
 fn main() {
     mimirsbrunn::utils::launch_run(run);
 }
@@ -30,14 +30,20 @@
 
 use cosmogony::{Zone, ZoneIndex};
 use failure::Error;
+use mimir::embedded::EmbeddedStore;
 use mimir::objects::Admin;
-use mimir::rubber::{IndexSettings, Rubber};
+use mimir::rubber::{IndexSettings, IndexVisibility, Rubber};
+use mimir::store::PlaceStore;
 use mimirsbrunn::osm_reader::admin;
 use mimirsbrunn::osm_reader::osm_utils;
 use mimirsbrunn::utils;
+use mimirsbrunn::utils::{
+    import_info, read_synonyms, BulkArgs, ConnectionArgs, PublicationChecksArgs,
+};
 use slog_scope::{info, warn};
 use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
 use structopt::StructOpt;
 
@@ -149,13 +155,21 @@ impl IntoAdmin for Zone {
 
 fn send_to_es(
     admins: impl Iterator<Item = Admin>,
-    cnx_string: &str,
+    mut rubber: Rubber,
     dataset: &str,
     index_settings: IndexSettings,
 ) -> Result<(), Error> {
-    let mut rubber = Rubber::new(cnx_string);
     rubber.initialize_templates()?;
-    let nb_admins = rubber.public_index(dataset, &index_settings, admins)?;
+    send_to_store(admins, &mut rubber, dataset, index_settings)
+}
+
+fn send_to_store<S: PlaceStore>(
+    admins: impl Iterator<Item = Admin>,
+    store: &mut S,
+    dataset: &str,
+    index_settings: IndexSettings,
+) -> Result<(), Error> {
+    let nb_admins = store.index(dataset, IndexVisibility::Public, &index_settings, admins)?;
     info!("{} admins added.", nb_admins);
     Ok(())
 }
@@ -209,16 +223,32 @@ fn index_cosmogony(args: Args) -> Result<(), Error> {
         )
     });
 
-    let index_settings = IndexSettings {
+    let mut index_settings = IndexSettings {
         nb_shards: args.nb_shards,
         nb_replicas: args.nb_replicas,
+        nb_retained_indexes: args.nb_retained_indexes,
+        publication_checks: (&args.publication_checks).into(),
+        synonyms: read_synonyms(args.synonyms.as_ref())?,
     };
-    send_to_es(
-        admins,
-        &args.connection_string,
-        &args.dataset,
-        index_settings,
-    )?;
+    // the countries are not attached to any administrative region
+    index_settings.publication_checks.max_no_admin_ratio = None;
+    match args.embedded_index {
+        Some(ref dir) => send_to_store(
+            admins,
+            &mut EmbeddedStore::open(dir)?,
+            &args.dataset,
+            index_settings,
+        )?,
+        None => send_to_es(
+            admins,
+            args.connection
+                .rubber(&args.connection_string)?
+                .with_bulk_settings((&args.bulk).into())
+                .with_import_info(import_info(&[&args.input])?),
+            &args.dataset,
+            index_settings,
+        )?,
+    }
 
     Ok(())
 }
@@ -244,6 +274,20 @@ struct Args {
     /// Number of replicas for the es index
     #[structopt(short = "r", long = "nb-replicas", default_value = "1")]
     nb_replicas: usize,
+    /// Number of previous indexes kept (without any alias) after the import,
+    /// to be able to rollback to them with mimir_rollback
+    #[structopt(long = "nb-retained-indexes", default_value = "0")]
+    nb_retained_indexes: usize,
+    /// Synonyms file, in the Solr format (one rule by line, like `st,saint`).
+    /// By default a list of French synonyms is used.
+    #[structopt(long = "synonyms", parse(from_os_str))]
+    synonyms: Option<PathBuf>,
+    #[structopt(flatten)]
+    publication_checks: PublicationChecksArgs,
+    #[structopt(flatten)]
+    bulk: BulkArgs,
+    #[structopt(flatten)]
+    connection: ConnectionArgs,
     /// Languages codes, used to build i18n names and labels
     #[structopt(name = "lang", short, long)]
     langs: Vec<String>,
@@ -252,6 +296,10 @@ struct Args {
     /// instead of 'admin:osm:{osm_id}'
     #[structopt(long = "french-id-retrocompatibility")]
     french_id_retrocompatibility: bool,
+    /// Directory of an embedded index to import the admins into,
+    /// instead of elasticsearch.
+    #[structopt(long = "embedded-index", parse(from_os_str))]
+    embedded_index: Option<PathBuf>,
 }
 
 fn main() {
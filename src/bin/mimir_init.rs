@@ -30,7 +30,7 @@
 
 use slog_scope::info;
 
-use mimir::rubber::Rubber;
+use mimirsbrunn::utils::ConnectionArgs;
 use structopt::StructOpt;
 
 #[derive(StructOpt, Debug)]
@@ -42,11 +42,13 @@ struct Args {
         default_value = "http://localhost:9200/"
     )]
     connection_string: String,
+    #[structopt(flatten)]
+    connection: ConnectionArgs,
 }
 
 fn run(args: Args) -> Result<(), failure::Error> {
     info!("creating templates");
-    let rubber = Rubber::new(&args.connection_string);
+    let mut rubber = args.connection.rubber(&args.connection_string)?;
     rubber.initialize_templates()
 }
 
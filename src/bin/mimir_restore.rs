@@ -0,0 +1,86 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use mimir::rubber::IndexSettings;
+use mimirsbrunn::utils::{import_info, BulkArgs, ConnectionArgs};
+use slog_scope::info;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+struct Args {
+    /// Directory of the dump, as written by mimir_dump.
+    #[structopt(short = "i", long = "input", parse(from_os_str))]
+    input: PathBuf,
+    /// Elasticsearch parameters.
+    #[structopt(
+        short = "c",
+        long = "connection-string",
+        default_value = "http://localhost:9200/"
+    )]
+    connection_string: String,
+    /// Number of shards for the es indexes
+    #[structopt(short = "s", long = "nb-shards", default_value = "1")]
+    nb_shards: usize,
+    /// Number of replicas for the es indexes
+    #[structopt(short = "r", long = "nb-replicas", default_value = "1")]
+    nb_replicas: usize,
+    /// Number of previous indexes kept (without any alias) after the import,
+    /// to be able to rollback to them with mimir_rollback
+    #[structopt(long = "nb-retained-indexes", default_value = "0")]
+    nb_retained_indexes: usize,
+    #[structopt(flatten)]
+    bulk: BulkArgs,
+    #[structopt(flatten)]
+    connection: ConnectionArgs,
+}
+
+fn run(args: Args) -> Result<(), failure::Error> {
+    info!("restoring the dump {:?}", args.input);
+    let mut rubber = args
+        .connection
+        .rubber(&args.connection_string)?
+        .with_bulk_settings((&args.bulk).into())
+        .with_import_info(import_info(&[&args.input])?);
+    rubber.initialize_templates()?;
+    let index_settings = IndexSettings {
+        nb_shards: args.nb_shards,
+        nb_replicas: args.nb_replicas,
+        nb_retained_indexes: args.nb_retained_indexes,
+        ..Default::default()
+    };
+    let manifest = mimirsbrunn::dump::restore(&mut rubber, &args.input, &index_settings)?;
+    info!("{} indexes restored", manifest.indexes.len());
+    Ok(())
+}
+
+fn main() {
+    mimirsbrunn::utils::launch_run(run);
+}
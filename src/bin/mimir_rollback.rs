@@ -0,0 +1,96 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use failure::bail;
+use mimir::rubber::Rubber;
+use mimir::{Addr, Admin, Line, MimirObject, PlaceKind, Poi, Stop, Street};
+use mimirsbrunn::utils::ConnectionArgs;
+use slog_scope::info;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+struct Args {
+    /// Type of the documents of the index (admin, street, addr, poi, stop or line).
+    #[structopt(short = "t", long = "type")]
+    doc_type: String,
+    /// Name of the dataset.
+    #[structopt(short = "d", long = "dataset", default_value = "fr")]
+    dataset: String,
+    /// Previous index to publish. By default the most recent index older than the
+    /// current one is used.
+    #[structopt(short = "i", long = "index")]
+    index: Option<String>,
+    /// Only list the previous indexes that can be published.
+    #[structopt(long = "list")]
+    list: bool,
+    /// Elasticsearch parameters.
+    #[structopt(
+        short = "c",
+        long = "connection-string",
+        default_value = "http://localhost:9200/"
+    )]
+    connection_string: String,
+    #[structopt(flatten)]
+    connection: ConnectionArgs,
+}
+
+fn rollback<T: MimirObject>(rubber: &Rubber, args: &Args) -> Result<(), failure::Error> {
+    if args.list {
+        let previous_indexes = rubber.get_previous_indexes::<T>(&args.dataset)?;
+        info!(
+            "previous indexes of {} for {}: {:?}",
+            T::doc_type(),
+            args.dataset,
+            previous_indexes
+        );
+        return Ok(());
+    }
+    let index =
+        rubber.rollback_index::<T>(&args.dataset, args.index.as_ref().map(|i| i.as_str()))?;
+    info!("{} is now the published index", index);
+    Ok(())
+}
+
+fn run(args: Args) -> Result<(), failure::Error> {
+    let rubber = args.connection.rubber(&args.connection_string)?;
+    match PlaceKind::from_es_name(&args.doc_type) {
+        Some(PlaceKind::Admin) => rollback::<Admin>(&rubber, &args),
+        Some(PlaceKind::Street) => rollback::<Street>(&rubber, &args),
+        Some(PlaceKind::Addr) => rollback::<Addr>(&rubber, &args),
+        Some(PlaceKind::Poi) => rollback::<Poi>(&rubber, &args),
+        Some(PlaceKind::Stop) => rollback::<Stop>(&rubber, &args),
+        Some(PlaceKind::Line) => rollback::<Line>(&rubber, &args),
+        None => bail!("unknown type {}", args.doc_type),
+    }
+}
+
+fn main() {
+    mimirsbrunn::utils::launch_run(run);
+}
@@ -31,6 +31,9 @@
 use failure::ResultExt;
 use mimir::rubber::IndexSettings;
 use mimirsbrunn::stops::*;
+use mimirsbrunn::utils::{
+    import_info, read_synonyms, BulkArgs, ConnectionArgs, PublicationChecksArgs,
+};
 use slog_scope::{info, warn};
 use std::path::PathBuf;
 use structopt::StructOpt;
@@ -61,6 +64,20 @@ struct Args {
     /// Number of replicas for the es index
     #[structopt(short = "r", long = "nb-replicas", default_value = "1")]
     nb_replicas: usize,
+    /// Number of previous indexes kept (without any alias) after the import,
+    /// to be able to rollback to them with mimir_rollback
+    #[structopt(long = "nb-retained-indexes", default_value = "0")]
+    nb_retained_indexes: usize,
+    /// Synonyms file, in the Solr format (one rule by line, like `st,saint`).
+    /// By default a list of French synonyms is used.
+    #[structopt(long = "synonyms", parse(from_os_str))]
+    synonyms: Option<PathBuf>,
+    #[structopt(flatten)]
+    publication_checks: PublicationChecksArgs,
+    #[structopt(flatten)]
+    bulk: BulkArgs,
+    #[structopt(flatten)]
+    connection: ConnectionArgs,
 }
 
 fn get_lines(idx: Idx<navitia::StopArea>, navitia: &transit_model::Model) -> Vec<mimir::Line> {
@@ -158,6 +175,51 @@ fn to_mimir(
     }
 }
 
+/// the line as a place, located on the stop areas it serves
+fn line_to_mimir(idx: Idx<navitia::Line>, navitia: &transit_model::Model) -> mimir::Line {
+    use geo::bounding_rect::BoundingRect;
+    use mimir::FromTransitModel;
+    let mut line = mimir::Line::from_transit_model(idx, navitia);
+    let stop_areas: Vec<_> = navitia
+        .get_corresponding_from_idx::<_, navitia::StopArea>(idx)
+        .into_iter()
+        .map(|sa_idx| &navitia.stop_areas[sa_idx])
+        .collect();
+    let points: geo::MultiPoint<f64> = stop_areas
+        .iter()
+        .map(|sa| geo::Point::new(sa.coord.lon, sa.coord.lat))
+        .collect::<Vec<_>>()
+        .into();
+    line.bbox = points.bounding_rect();
+    if let Some(bbox) = line.bbox {
+        let coord = mimir::Coord::new(
+            (bbox.min.x + bbox.max.x) / 2.,
+            (bbox.min.y + bbox.max.y) / 2.,
+        );
+        line.approx_coord = Some(coord.clone().into());
+        line.coord = coord;
+    }
+    line.stop_areas = stop_areas
+        .iter()
+        .map(|sa| format!("stop_area:{}", sa.id))
+        .collect();
+    line.label = line_label(&line);
+    line
+}
+
+// like "Métro 14 (RATP)", the name of the line is used when it has no code
+fn line_label(line: &mimir::Line) -> String {
+    let mut label = match (&line.commercial_mode, &line.code) {
+        (Some(mode), Some(code)) => format!("{} {}", mode.name, code),
+        (None, Some(code)) => code.clone(),
+        (_, None) => line.name.clone(),
+    };
+    if let Some(ref network) = line.network {
+        label = format!("{} ({})", label, network.name);
+    }
+    label
+}
+
 fn main() {
     mimirsbrunn::utils::launch_run(run);
 }
@@ -187,21 +249,37 @@ fn run(args: Args) -> Result<(), transit_model::Error> {
         .map(|(idx, sa)| to_mimir(idx, sa, &navitia))
         .collect();
     set_weights(stops.iter_mut(), &nb_stop_points);
+    let mut lines: Vec<mimir::Line> = navitia
+        .lines
+        .iter()
+        .map(|(idx, _)| line_to_mimir(idx, &navitia))
+        .collect();
+    set_line_weights(&mut lines);
 
     let index_settings = IndexSettings {
         nb_shards: args.nb_shards,
         nb_replicas: args.nb_replicas,
+        nb_retained_indexes: args.nb_retained_indexes,
+        publication_checks: (&args.publication_checks).into(),
+        synonyms: read_synonyms(args.synonyms.as_ref())?,
     };
 
-    import_stops(
-        stops,
-        &args.connection_string,
-        &args.dataset,
-        index_settings,
-    )
-    .with_context(|_| {
+    let rubber = args
+        .connection
+        .rubber(&args.connection_string)?
+        .with_bulk_settings((&args.bulk).into())
+        .with_import_info(import_info(&[&args.input])?);
+    import_stops(stops, rubber.clone(), &args.dataset, index_settings.clone()).with_context(
+        |_| {
+            format!(
+                "Error occurred when importing stops into {} on {}",
+                args.dataset, args.connection_string
+            )
+        },
+    )?;
+    import_lines(lines, rubber, &args.dataset, index_settings).with_context(|_| {
         format!(
-            "Error occurred when importing stops into {} on {}",
+            "Error occurred when importing lines into {} on {}",
             args.dataset, args.connection_string
         )
     })?;
@@ -217,6 +295,11 @@ fn test_bad_connection_string() {
         city_level: None,
         nb_replicas: 1,
         nb_shards: 1,
+        nb_retained_indexes: 0,
+        publication_checks: Default::default(),
+        bulk: Default::default(),
+        connection: Default::default(),
+        synonyms: None,
     };
     let causes = run(args)
         .unwrap_err()
@@ -228,7 +311,8 @@ fn test_bad_connection_string() {
         causes,
         [
             "Error occurred when importing stops into bob on http://localhost:1".to_string(),
-            "Error: http://localhost:1/_template/template_addr: error trying to connect: Connection refused (os error 111) while creating template template_addr"
+            "Error occurred when getting the elasticsearch version".to_string(),
+            "http://localhost:1/: error trying to connect: Connection refused (os error 111)"
                 .to_string(),
         ]
     );
@@ -243,6 +327,11 @@ fn test_bad_file() {
         city_level: None,
         nb_replicas: 1,
         nb_shards: 1,
+        nb_retained_indexes: 0,
+        publication_checks: Default::default(),
+        bulk: Default::default(),
+        connection: Default::default(),
+        synonyms: None,
     };
     let causes = run(args)
         .unwrap_err()
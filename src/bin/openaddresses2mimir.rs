@@ -29,9 +29,14 @@
 // www.navitia.io
 
 use lazy_static::lazy_static;
-use mimir::rubber::{IndexSettings, Rubber};
-use mimirsbrunn::addr_reader::import_addresses;
+use mimir::embedded::EmbeddedStore;
+use mimir::rubber::IndexSettings;
+use mimir::store::PlaceStore;
+use mimirsbrunn::addr_reader::{import_addresses, read_addresses};
 use mimirsbrunn::admin_geofinder::AdminGeoFinder;
+use mimirsbrunn::utils::{
+    import_info, read_synonyms, BulkArgs, ConnectionArgs, PublicationChecksArgs,
+};
 use mimirsbrunn::{labels, utils};
 use serde::{Deserialize, Serialize};
 use slog_scope::{info, warn};
@@ -138,20 +143,20 @@ impl OpenAddresse {
     }
 }
 
-fn index_oa<I>(
-    cnx_string: &str,
+fn index_oa<S, I>(
+    store: &mut S,
     dataset: &str,
     index_settings: IndexSettings,
     files: I,
     nb_threads: usize,
     use_old_index_format: bool,
+    incremental: bool,
 ) -> Result<(), mimirsbrunn::Error>
 where
+    S: PlaceStore,
     I: Iterator<Item = std::path::PathBuf>,
 {
-    let mut rubber = Rubber::new(cnx_string);
-
-    let admins = rubber.get_all_admins().unwrap_or_else(|err| {
+    let admins = store.get_all_admins().unwrap_or_else(|err| {
         warn!(
             "Administratives regions not found in es db for dataset {}. (error: {})",
             dataset, err
@@ -160,15 +165,10 @@ where
     });
     let admins_geofinder = admins.into_iter().collect();
 
-    import_addresses(
-        &mut rubber,
-        true,
-        nb_threads,
-        index_settings,
-        dataset,
-        files,
-        move |a: OpenAddresse| a.into_addr(&admins_geofinder, use_old_index_format),
-    )
+    let addresses = read_addresses(true, nb_threads, files, move |a: OpenAddresse| {
+        a.into_addr(&admins_geofinder, use_old_index_format)
+    });
+    import_addresses(store, incremental, index_settings, dataset, addresses)
 }
 
 #[derive(StructOpt, Debug)]
@@ -202,22 +202,41 @@ struct Args {
     /// Number of replicas for the es index
     #[structopt(short = "r", long = "nb-replicas", default_value = "1")]
     nb_replicas: usize,
+    /// Number of previous indexes kept (without any alias) after the import,
+    /// to be able to rollback to them with mimir_rollback
+    #[structopt(long = "nb-retained-indexes", default_value = "0")]
+    nb_retained_indexes: usize,
+    /// Synonyms file, in the Solr format (one rule by line, like `st,saint`).
+    /// By default a list of French synonyms is used.
+    #[structopt(long = "synonyms", parse(from_os_str))]
+    synonyms: Option<PathBuf>,
+    #[structopt(flatten)]
+    publication_checks: PublicationChecksArgs,
+    #[structopt(flatten)]
+    bulk: BulkArgs,
+    #[structopt(flatten)]
+    connection: ConnectionArgs,
     /// If set to true, the number inside the address won't be used for the index generation,
     /// therefore, different addresses with the same position will disappear.
     #[structopt(long = "use-old-index-format")]
     use_old_index_format: bool,
+    /// If set to true, only the addresses that changed are sent to a copy of the
+    /// published index, instead of sending all of them to a new index.
+    #[structopt(long = "incremental")]
+    incremental: bool,
+    /// Directory of an embedded index to import the addresses into,
+    /// instead of elasticsearch.
+    #[structopt(long = "embedded-index", parse(from_os_str))]
+    embedded_index: Option<PathBuf>,
 }
 
-fn run(args: Args) -> Result<(), failure::Error> {
-    info!("importing open addresses into Mimir");
-
-    if args.city_level.is_some() {
-        warn!("city-level option is deprecated, it now has no effect.");
-    }
-
+fn import_oa<S: PlaceStore>(store: &mut S, args: Args) -> Result<(), failure::Error> {
     let index_settings = IndexSettings {
         nb_shards: args.nb_shards,
         nb_replicas: args.nb_replicas,
+        nb_retained_indexes: args.nb_retained_indexes,
+        publication_checks: (&args.publication_checks).into(),
+        synonyms: read_synonyms(args.synonyms.as_ref())?,
     };
     if args.input.is_dir() {
         let paths = walkdir::WalkDir::new(&args.input);
@@ -236,25 +255,48 @@ fn run(args: Args) -> Result<(), failure::Error> {
                 f
             });
         index_oa(
-            &args.connection_string,
+            store,
             &args.dataset,
             index_settings,
             path_iter,
             args.nb_threads,
             args.use_old_index_format,
+            args.incremental,
         )
     } else {
         index_oa(
-            &args.connection_string,
+            store,
             &args.dataset,
             index_settings,
             std::iter::once(args.input),
             args.nb_threads,
             args.use_old_index_format,
+            args.incremental,
         )
     }
 }
 
+fn run(args: Args) -> Result<(), failure::Error> {
+    info!("importing open addresses into Mimir");
+
+    if args.city_level.is_some() {
+        warn!("city-level option is deprecated, it now has no effect.");
+    }
+
+    match args.embedded_index.clone() {
+        Some(dir) => import_oa(&mut EmbeddedStore::open(dir)?, args),
+        None => {
+            let mut rubber = args
+                .connection
+                .rubber(&args.connection_string)?
+                .with_bulk_settings((&args.bulk).into())
+                .with_import_info(import_info(&[&args.input])?);
+            rubber.detect_mapping_mode()?;
+            import_oa(&mut rubber, args)
+        }
+    }
+}
+
 fn main() {
     mimirsbrunn::utils::launch_run(run);
 }
@@ -29,12 +29,17 @@
 // www.navitia.io
 
 use failure::ResultExt;
-use mimir::rubber::{IndexSettings, Rubber};
+use mimir::embedded::EmbeddedStore;
+use mimir::rubber::{IndexSettings, IndexVisibility};
+use mimir::store::PlaceStore;
 use mimirsbrunn::admin_geofinder::AdminGeoFinder;
 use mimirsbrunn::osm_reader::admin::read_administrative_regions;
 use mimirsbrunn::osm_reader::make_osm_reader;
 use mimirsbrunn::osm_reader::poi::{add_address, compute_poi_weight, pois, PoiConfig};
 use mimirsbrunn::osm_reader::street::{compute_street_weight, streets};
+use mimirsbrunn::utils::{
+    import_info, read_synonyms, BulkArgs, ConnectionArgs, PublicationChecksArgs,
+};
 use slog_scope::{debug, info};
 use std::path::PathBuf;
 use structopt::StructOpt;
@@ -90,24 +95,56 @@ struct Args {
     /// Number of replicas for the es index
     #[structopt(long = "nb-poi-replicas", default_value = "1")]
     nb_poi_replicas: usize,
+    /// Number of previous indexes kept (without any alias) after the import,
+    /// to be able to rollback to them with mimir_rollback
+    #[structopt(long = "nb-retained-indexes", default_value = "0")]
+    nb_retained_indexes: usize,
+    /// Synonyms file, in the Solr format (one rule by line, like `st,saint`).
+    /// By default a list of French synonyms is used.
+    #[structopt(long = "synonyms", parse(from_os_str))]
+    synonyms: Option<PathBuf>,
+    #[structopt(flatten)]
+    publication_checks: PublicationChecksArgs,
+    #[structopt(flatten)]
+    bulk: BulkArgs,
+    #[structopt(flatten)]
+    connection: ConnectionArgs,
+    /// Directory of an embedded index to import the data into,
+    /// instead of elasticsearch.
+    #[structopt(long = "embedded-index", parse(from_os_str))]
+    embedded_index: Option<PathBuf>,
 }
 
 fn run(args: Args) -> Result<(), mimirsbrunn::Error> {
+    match args.embedded_index.clone() {
+        Some(dir) => import_osm(&mut EmbeddedStore::open(dir)?, args),
+        None => {
+            debug!("creation of indexes");
+            let mut rubber = args
+                .connection
+                .rubber(&args.connection_string)?
+                .with_bulk_settings((&args.bulk).into())
+                .with_import_info(import_info(&[&args.input])?);
+            rubber.initialize_templates()?;
+            import_osm(&mut rubber, args)
+        }
+    }
+}
+
+fn import_osm<S: PlaceStore>(store: &mut S, args: Args) -> Result<(), mimirsbrunn::Error> {
     let levels = args.level.iter().cloned().collect();
     let city_level = args.city_level;
 
     let mut osm_reader = make_osm_reader(&args.input)?;
-    debug!("creation of indexes");
-    let mut rubber = Rubber::new(&args.connection_string);
-    rubber.initialize_templates()?;
 
     info!("creating adminstrative regions");
     let admins = if args.import_admin {
         read_administrative_regions(&mut osm_reader, levels, city_level)
     } else {
-        rubber.get_all_admins()?
+        store.get_all_admins()?
     };
     let admins_geofinder = admins.into_iter().collect::<AdminGeoFinder>();
+    let synonyms = read_synonyms(args.synonyms.as_ref())?;
     {
         info!("Extracting streets from osm");
         let mut streets = streets(&mut osm_reader, &admins_geofinder)?;
@@ -119,10 +156,18 @@ fn run(args: Args) -> Result<(), mimirsbrunn::Error> {
             let street_index_settings = IndexSettings {
                 nb_shards: args.nb_street_shards,
                 nb_replicas: args.nb_street_replicas,
+                nb_retained_indexes: args.nb_retained_indexes,
+                publication_checks: (&args.publication_checks).into(),
+                synonyms: synonyms.clone(),
             };
             info!("importing streets into Mimir");
-            let nb_streets = rubber
-                .public_index(&args.dataset, &street_index_settings, streets.into_iter())
+            let nb_streets = store
+                .index(
+                    &args.dataset,
+                    IndexVisibility::Public,
+                    &street_index_settings,
+                    streets.into_iter(),
+                )
                 .with_context(|_| {
                     format!(
                         "Error occurred when requesting street number in {}",
@@ -133,13 +178,19 @@ fn run(args: Args) -> Result<(), mimirsbrunn::Error> {
         }
     }
     if args.import_admin {
-        let admin_index_settings = IndexSettings {
+        let mut admin_index_settings = IndexSettings {
             nb_shards: args.nb_admin_shards,
             nb_replicas: args.nb_admin_replicas,
+            nb_retained_indexes: args.nb_retained_indexes,
+            publication_checks: (&args.publication_checks).into(),
+            synonyms: synonyms.clone(),
         };
-        let nb_admins = rubber
-            .public_index(
+        // the admins read from osm are not attached to their parent administrative regions
+        admin_index_settings.publication_checks.max_no_admin_ratio = None;
+        let nb_admins = store
+            .index(
                 &args.dataset,
+                IndexVisibility::Public,
                 &admin_index_settings,
                 admins_geofinder.admins(),
             )
@@ -169,15 +220,23 @@ fn run(args: Args) -> Result<(), mimirsbrunn::Error> {
         compute_poi_weight(&mut pois);
 
         info!("Adding addresss in poi");
-        add_address(&mut pois, &mut rubber);
+        add_address(&mut pois, store);
 
         let poi_index_settings = IndexSettings {
             nb_shards: args.nb_poi_shards,
             nb_replicas: args.nb_poi_replicas,
+            nb_retained_indexes: args.nb_retained_indexes,
+            publication_checks: (&args.publication_checks).into(),
+            synonyms,
         };
         info!("Importing pois into Mimir");
-        let nb_pois = rubber
-            .public_index(&args.dataset, &poi_index_settings, pois.into_iter())
+        let nb_pois = store
+            .index(
+                &args.dataset,
+                IndexVisibility::Public,
+                &poi_index_settings,
+                pois.into_iter(),
+            )
             .context("Importing pois into Mimir")?;
 
         info!("Nb of indexed pois: {}", nb_pois);
@@ -32,8 +32,11 @@ use slog_scope::info;
 
 use failure::format_err;
 use lazy_static::lazy_static;
-use mimir::objects::{Coord, I18nProperties, Poi, PoiType, Property};
+use mimir::objects::{Coord, I18nProperties, Place, Poi, PoiType, Property};
 use mimir::rubber::{IndexSettings, IndexVisibility, Rubber, TypedIndex};
+use mimirsbrunn::utils::{
+    import_info, read_synonyms, BulkArgs, ConnectionArgs, PublicationChecksArgs,
+};
 use mimirsbrunn::{labels, utils};
 use navitia_poi_model::{Model as NavitiaModel, Poi as NavitiaPoi, PoiType as NavitiaPoiType};
 use std::collections::HashMap;
@@ -46,11 +49,12 @@ lazy_static! {
 }
 
 // This function takes a Poi from the navitia model, ie from the CSV deserialization, and returns
-// a Poi from the mimir model, with all the contextual information added.
+// a Poi from the mimir model, with all the contextual information added
+// (from `place`, the address found next to the Poi).
 fn into_mimir_poi(
     poi: NavitiaPoi,
     poi_types: &HashMap<String, NavitiaPoiType>,
-    rubber: &mut Rubber,
+    place: Option<Place>,
 ) -> Result<Poi, mimirsbrunn::Error> {
     let poi_type = poi_types
         .get(&poi.poi_type_id)
@@ -59,11 +63,6 @@ fn into_mimir_poi(
 
     let coord = Coord::from(&poi.coord);
 
-    let place = rubber
-        .get_address(&coord) // No timeout
-        .ok()
-        .and_then(|addrs| addrs.into_iter().next()); // Take the first place
-
     let addr = place.as_ref().and_then(|addr| addr.address());
 
     let admins = place.map_or_else(|| Vec::new(), |addr| addr.admins());
@@ -123,14 +122,28 @@ where
     let model = NavitiaModel::try_from_path(file)?;
     let poi_types = model.poi_types;
 
+    // the addresses of all the POIs are searched at once, by batches
+    // (the POIs of a failed search have no address)
+    let pois: Vec<_> = model.pois.into_iter().collect();
+    let coords: Vec<_> = pois
+        .iter()
+        .map(|(_, poi)| Coord::from(&poi.coord))
+        .collect();
+    let addresses = rubber.get_addresses(&coords); // No timeout
+
     // Note: We're ignoring those POIs that fail to be enriched.
-    let pois: Vec<_> = model
-        .pois
+    let pois: Vec<_> = pois
         .into_iter()
-        .filter_map(|(id, poi)| {
-            into_mimir_poi(poi, &poi_types, rubber)
-                .map_err(|err| info!("Could not extract information for POI '{}': {}", id, err))
-                .ok()
+        .zip(addresses)
+        .filter_map(|((id, poi), addrs)| {
+            // Take the first place
+            into_mimir_poi(
+                poi,
+                &poi_types,
+                addrs.and_then(|addrs| addrs.into_iter().next()),
+            )
+            .map_err(|err| info!("Could not extract information for POI '{}': {}", id, err))
+            .ok()
         })
         .collect(); // TODO Can we get rid of collect, and chain with the following rubber...?
 
@@ -146,29 +159,22 @@ where
 /// This function initializes the ES context: It creates an index for this dataset,
 /// and then import the POIs in it.
 fn index_poi(
-    cnx_string: &str,
+    mut rubber: Rubber,
     dataset: &str,
     file: &PathBuf,
     visibility: IndexVisibility,
-    nb_shards: usize,
-    nb_replicas: usize,
+    settings: IndexSettings,
 ) -> Result<(), mimirsbrunn::Error>
 where
 {
-    let mut rubber = Rubber::new(cnx_string);
     rubber.initialize_templates()?;
 
-    let settings = IndexSettings {
-        nb_shards: nb_shards,
-        nb_replicas: nb_replicas,
-    };
-
     let index = rubber.make_index(dataset, &settings)?;
 
     import_pois(&mut rubber, &index, file)?;
 
     rubber
-        .publish_index(dataset, index, visibility)
+        .publish_index(dataset, index, visibility, &settings)
         .map_err(|err| format_err!("Failed to publish index {}.", err))
 }
 
@@ -210,6 +216,20 @@ struct Args {
     /// Number of replicas for the es index
     #[structopt(short = "r", long = "nb-replicas", default_value = "1")]
     nb_replicas: usize,
+    /// Number of previous indexes kept (without any alias) after the import,
+    /// to be able to rollback to them with mimir_rollback
+    #[structopt(long = "nb-retained-indexes", default_value = "0")]
+    nb_retained_indexes: usize,
+    /// Synonyms file, in the Solr format (one rule by line, like `st,saint`).
+    /// By default a list of French synonyms is used.
+    #[structopt(long = "synonyms", parse(from_os_str))]
+    synonyms: Option<PathBuf>,
+    #[structopt(flatten)]
+    publication_checks: PublicationChecksArgs,
+    #[structopt(flatten)]
+    bulk: BulkArgs,
+    #[structopt(flatten)]
+    connection: ConnectionArgs,
 }
 
 fn run(args: Args) -> Result<(), mimirsbrunn::Error> {
@@ -219,13 +239,23 @@ fn run(args: Args) -> Result<(), mimirsbrunn::Error> {
         IndexVisibility::Public
     };
 
+    let rubber = args
+        .connection
+        .rubber(&args.connection_string)?
+        .with_bulk_settings((&args.bulk).into())
+        .with_import_info(import_info(&[&args.input])?);
     index_poi(
-        &args.connection_string,
+        rubber,
         &args.dataset,
         &args.input,
         visibility,
-        args.nb_shards,
-        args.nb_replicas,
+        IndexSettings {
+            nb_shards: args.nb_shards,
+            nb_replicas: args.nb_replicas,
+            nb_retained_indexes: args.nb_retained_indexes,
+            publication_checks: (&args.publication_checks).into(),
+            synonyms: read_synonyms(args.synonyms.as_ref())?,
+        },
     )
 }
 fn main() {
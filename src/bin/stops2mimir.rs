@@ -31,6 +31,9 @@
 use failure::ResultExt;
 use mimir::rubber::IndexSettings;
 use mimirsbrunn::stops::*;
+use mimirsbrunn::utils::{
+    import_info, read_synonyms, BulkArgs, ConnectionArgs, PublicationChecksArgs,
+};
 use serde::Deserialize;
 use slog_scope::{info, warn};
 use std::collections::HashMap;
@@ -67,6 +70,20 @@ struct Args {
     /// Number of replicas for the es index
     #[structopt(short = "r", long = "nb-replicas", default_value = "1")]
     nb_replicas: usize,
+    /// Number of previous indexes kept (without any alias) after the import,
+    /// to be able to rollback to them with mimir_rollback
+    #[structopt(long = "nb-retained-indexes", default_value = "0")]
+    nb_retained_indexes: usize,
+    /// Synonyms file, in the Solr format (one rule by line, like `st,saint`).
+    /// By default a list of French synonyms is used.
+    #[structopt(long = "synonyms", parse(from_os_str))]
+    synonyms: Option<PathBuf>,
+    #[structopt(flatten)]
+    publication_checks: PublicationChecksArgs,
+    #[structopt(flatten)]
+    bulk: BulkArgs,
+    #[structopt(flatten)]
+    connection: ConnectionArgs,
 }
 
 #[derive(Deserialize, Debug)]
@@ -163,15 +180,18 @@ fn run(args: Args) -> Result<(), failure::Error> {
     let index_settings = IndexSettings {
         nb_shards: args.nb_shards,
         nb_replicas: args.nb_replicas,
+        nb_retained_indexes: args.nb_retained_indexes,
+        publication_checks: (&args.publication_checks).into(),
+        synonyms: read_synonyms(args.synonyms.as_ref())?,
     };
 
-    import_stops(
-        stops,
-        &args.connection_string,
-        &args.dataset,
-        index_settings,
-    )
-    .context("Error while importing stops")?;
+    let rubber = args
+        .connection
+        .rubber(&args.connection_string)?
+        .with_bulk_settings((&args.bulk).into())
+        .with_import_info(import_info(&[&args.input])?);
+    import_stops(stops, rubber, &args.dataset, index_settings)
+        .context("Error while importing stops")?;
     Ok(())
 }
 
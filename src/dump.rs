@@ -0,0 +1,393 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+//! Export of the munin indexes into flat files, and import of these files
+//! into another elasticsearch cluster.
+//!
+//! A dump is a directory with a `manifest.json` describing the dumped indexes
+//! (kind of documents, dataset, visibility, aliases and metadata) and, for each index,
+//! a gzipped newline-delimited json file with all its documents.
+
+use failure::{format_err, Error, ResultExt};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use mimir::rubber::{
+    IndexMetadata, IndexSettings, IndexVisibility, Rubber, ScrollSettings, TypedIndex,
+};
+use mimir::{Addr, Admin, Line, MimirObject, PlaceKind, Poi, Stop, Street};
+use serde::{Deserialize, Serialize};
+use slog_scope::{info, warn};
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Description of a dumped index
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DumpedIndex {
+    /// name of the elasticsearch index
+    pub index: String,
+    /// kind of the documents of the index (the `MimirObject::doc_type`)
+    pub doc_type: String,
+    /// dataset of the index, None for the indexes not bound to a dataset (like the global stops)
+    pub dataset: Option<String>,
+    /// true if the index is aliased to the global indexes (cf `IndexVisibility`)
+    pub public: bool,
+    /// all the aliases of the index
+    pub aliases: Vec<String>,
+    /// synonyms used by the index analyzers
+    #[serde(default)]
+    pub synonyms: Vec<String>,
+    /// metadata of the index (cf `IndexMetadata`), written back when the index is restored
+    /// (None for the indexes without metadata, like the ones built by an older version)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<IndexMetadata>,
+    /// number of documents in the index
+    pub nb_documents: usize,
+    /// name of the documents file, relative to the dump directory
+    pub file: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Manifest {
+    pub indexes: Vec<DumpedIndex>,
+}
+
+impl Manifest {
+    pub fn read(dir: &Path) -> Result<Manifest, Error> {
+        let path = dir.join(MANIFEST_FILE);
+        let file = File::open(&path).with_context(|_| format!("impossible to open {:?}", path))?;
+        Ok(serde_json::from_reader(BufReader::new(file))
+            .with_context(|_| format!("invalid manifest {:?}", path))?)
+    }
+
+    fn write(&self, dir: &Path) -> Result<(), Error> {
+        let path = dir.join(MANIFEST_FILE);
+        let file =
+            File::create(&path).with_context(|_| format!("impossible to create {:?}", path))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self)?;
+        Ok(())
+    }
+}
+
+/// describe an index from its name and its aliases (cf `mimir::rubber::describe_index`)
+pub fn describe_index(index: &str, aliases: Vec<String>) -> Option<DumpedIndex> {
+    let description = mimir::rubber::describe_index(index, aliases)?;
+    Some(DumpedIndex {
+        file: format!("{}.jsonl.gz", description.index),
+        index: description.index,
+        doc_type: description.doc_type,
+        dataset: description.dataset,
+        public: description.public,
+        aliases: description.aliases,
+        synonyms: vec![],
+        metadata: None,
+        nb_documents: 0,
+    })
+}
+
+/// dump all the munin indexes in the `output` directory
+pub fn dump(rubber: &mut Rubber, output: &Path) -> Result<Manifest, Error> {
+    std::fs::create_dir_all(output)
+        .with_context(|_| format!("impossible to create directory {:?}", output))?;
+    let mut manifest = Manifest::default();
+    for (index, aliases) in rubber.get_all_aliased_index("munin_")? {
+        if aliases.is_empty() {
+            // not published indexes are either being built or ghosts of a failed import
+            warn!("index {} is not aliased, it is not dumped", index);
+            continue;
+        }
+        let mut dumped_index = match describe_index(&index, aliases) {
+            Some(dumped_index) => dumped_index,
+            None => {
+                warn!("unknown kind of index {}, it is not dumped", index);
+                continue;
+            }
+        };
+        info!("dumping index {}", index);
+        dumped_index.synonyms = rubber
+            .get_synonyms(&index)?
+            .remove(&index)
+            .unwrap_or_default();
+        dumped_index.metadata = rubber.get_index_metadata(&index)?.remove(&index);
+        let path = output.join(&dumped_index.file);
+        dumped_index.nb_documents = match PlaceKind::from_es_name(&dumped_index.doc_type) {
+            Some(PlaceKind::Admin) => dump_index::<Admin>(rubber, &index, &path),
+            Some(PlaceKind::Street) => dump_index::<Street>(rubber, &index, &path),
+            Some(PlaceKind::Addr) => dump_index::<Addr>(rubber, &index, &path),
+            Some(PlaceKind::Poi) => dump_index::<Poi>(rubber, &index, &path),
+            Some(PlaceKind::Stop) => dump_index::<Stop>(rubber, &index, &path),
+            Some(PlaceKind::Line) => dump_index::<Line>(rubber, &index, &path),
+            None => Err(format_err!(
+                "unknown document type {}",
+                dumped_index.doc_type
+            )),
+        }
+        .with_context(|_| format!("impossible to dump index {}", index))?;
+        info!(
+            "{} documents dumped from {}",
+            dumped_index.nb_documents, index
+        );
+        manifest.indexes.push(dumped_index);
+    }
+    manifest.write(output)?;
+    Ok(manifest)
+}
+
+fn dump_index<T>(rubber: &mut Rubber, index: &str, path: &Path) -> Result<usize, Error>
+where
+    for<'de> T: MimirObject + Deserialize<'de> + std::fmt::Debug,
+{
+    // the objects are written as they are fetched, so a big index is not loaded in memory
+    let objects = rubber.scroll_objects::<T>(index, &ScrollSettings::default())?;
+    let file = File::create(path).with_context(|_| format!("impossible to create {:?}", path))?;
+    let mut writer = GzEncoder::new(BufWriter::new(file), Compression::default());
+    let mut nb_objects = 0;
+    for object in objects {
+        serde_json::to_writer(&mut writer, &object?)?;
+        writer.write_all(b"\n")?;
+        nb_objects += 1;
+    }
+    writer.finish()?.flush()?;
+    Ok(nb_objects)
+}
+
+/// load all the indexes of the dump in the `input` directory
+/// the indexes are published with the same aliases as in the dumped cluster
+pub fn restore(
+    rubber: &mut Rubber,
+    input: &Path,
+    index_settings: &IndexSettings,
+) -> Result<Manifest, Error> {
+    let manifest = Manifest::read(input)?;
+    for dumped_index in &manifest.indexes {
+        info!("restoring index {}", dumped_index.index);
+        let nb_documents = match PlaceKind::from_es_name(&dumped_index.doc_type) {
+            Some(PlaceKind::Admin) => {
+                restore_index::<Admin>(rubber, input, dumped_index, index_settings)
+            }
+            Some(PlaceKind::Street) => {
+                restore_index::<Street>(rubber, input, dumped_index, index_settings)
+            }
+            Some(PlaceKind::Addr) => {
+                restore_index::<Addr>(rubber, input, dumped_index, index_settings)
+            }
+            Some(PlaceKind::Poi) => {
+                restore_index::<Poi>(rubber, input, dumped_index, index_settings)
+            }
+            Some(PlaceKind::Stop) => {
+                restore_index::<Stop>(rubber, input, dumped_index, index_settings)
+            }
+            Some(PlaceKind::Line) => {
+                restore_index::<Line>(rubber, input, dumped_index, index_settings)
+            }
+            None => Err(format_err!(
+                "unknown document type {}",
+                dumped_index.doc_type
+            )),
+        }
+        .with_context(|_| format!("impossible to restore index {}", dumped_index.index))?;
+        if nb_documents != dumped_index.nb_documents {
+            warn!(
+                "{} documents restored for {}, but {} were dumped",
+                nb_documents, dumped_index.index, dumped_index.nb_documents
+            );
+        }
+        info!(
+            "{} documents restored for {}",
+            nb_documents, dumped_index.index
+        );
+    }
+    Ok(manifest)
+}
+
+fn restore_index<T>(
+    rubber: &mut Rubber,
+    input: &Path,
+    dumped_index: &DumpedIndex,
+    index_settings: &IndexSettings,
+) -> Result<usize, Error>
+where
+    for<'de> T: MimirObject + Deserialize<'de> + Send + 'static,
+{
+    let path = input.join(&dumped_index.file);
+    let file = File::open(&path).with_context(|_| format!("impossible to open {:?}", path))?;
+
+    // the index is restored with the synonyms it was built with
+    let mut index_settings = index_settings.clone();
+    if !dumped_index.synonyms.is_empty() {
+        index_settings.synonyms = dumped_index.synonyms.clone();
+    }
+    let index_settings = &index_settings;
+
+    // the first invalid line stops the import, the index is then removed
+    let mut error: Option<Error> = None;
+    let documents = BufReader::new(GzDecoder::new(file))
+        .lines()
+        .scan(&mut error, |error, line| {
+            match line
+                .map_err(Error::from)
+                .and_then(|l| serde_json::from_str::<T>(&l).map_err(Error::from))
+            {
+                Ok(document) => Some(document),
+                Err(e) => {
+                    **error = Some(e);
+                    None
+                }
+            }
+        });
+
+    let index = match &dumped_index.dataset {
+        Some(dataset) => rubber.make_index::<T>(dataset, index_settings)?,
+        None => {
+            let base_name = dumped_index
+                .aliases
+                .first()
+                .ok_or_else(|| format_err!("index {} has no alias", dumped_index.index))?;
+            let index_name = mimir::rubber::get_date_index_name(base_name);
+            rubber.create_index(&index_name, index_settings)?;
+            TypedIndex::<T>::new(index_name)
+        }
+    };
+    let index_name = index.name().to_string();
+    let loaded = rubber
+        .bulk_index(&index, documents)
+        .and_then(|nb_documents| match error {
+            Some(e) => Err(e.context(format!("invalid file {:?}", path)).into()),
+            None => Ok(nb_documents),
+        });
+    let nb_documents = match loaded {
+        Ok(nb_documents) => nb_documents,
+        Err(e) => {
+            // the half-loaded index is removed
+            rubber.delete_index(&index_name)?;
+            return Err(e);
+        }
+    };
+
+    match &dumped_index.dataset {
+        Some(dataset) => {
+            let visibility = if dumped_index.public {
+                IndexVisibility::Public
+            } else {
+                IndexVisibility::Private
+            };
+            rubber.publish_index(dataset, index, visibility, index_settings)?;
+        }
+        None => {
+            rubber
+                .es_client
+                .refresh()
+                .with_indexes(&[&index_name])
+                .send()?;
+        }
+    }
+
+    // the index keeps the metadata of its original import, not the ones of the restoration
+    if let Some(metadata) = &dumped_index.metadata {
+        let metadata = IndexMetadata {
+            index: index_name.clone(),
+            ..metadata.clone()
+        };
+        rubber.put_index_metadata::<T>(&index_name, &metadata)?;
+    }
+
+    // the aliases not handled by the publication of the index (like the global stops alias)
+    // are moved to the new index, and the indexes they were pointing to are removed
+    let current_aliases = rubber
+        .get_all_aliased_index(&index_name)?
+        .remove(&index_name)
+        .unwrap_or_default();
+    let mut previous_indexes = BTreeSet::new();
+    for alias in dumped_index
+        .aliases
+        .iter()
+        .filter(|a| !current_aliases.contains(a))
+    {
+        let aliased_indexes: Vec<_> = rubber
+            .get_all_aliased_index(alias)?
+            .into_iter()
+            .filter(|(i, aliases)| *i != index_name && aliases.contains(alias))
+            .map(|(i, _)| i)
+            .collect();
+        rubber.alias(alias, &[index_name.clone()], &aliased_indexes)?;
+        previous_indexes.extend(aliased_indexes);
+    }
+    for index in previous_indexes {
+        rubber.delete_index(&index)?;
+    }
+    Ok(nb_documents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_index() {
+        let aliases = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        let addr = describe_index(
+            "munin_addr_fr_20190101_120000_000001",
+            aliases(&["munin_geo_data", "munin_addr_fr", "munin", "munin_addr"]),
+        )
+        .unwrap();
+        assert_eq!(addr.doc_type, "addr");
+        assert_eq!(addr.dataset, Some("fr".to_string()));
+        assert!(addr.public);
+        assert_eq!(
+            addr.aliases,
+            aliases(&["munin", "munin_addr", "munin_addr_fr", "munin_geo_data"])
+        );
+        assert_eq!(addr.file, "munin_addr_fr_20190101_120000_000001.jsonl.gz");
+
+        let private_poi = describe_index(
+            "munin_poi_my_pois_20190101_120000_000001",
+            aliases(&["munin_poi_my_pois"]),
+        )
+        .unwrap();
+        assert_eq!(private_poi.doc_type, "poi");
+        assert_eq!(private_poi.dataset, Some("my_pois".to_string()));
+        assert!(!private_poi.public);
+
+        let global_stops = describe_index(
+            "munin_global_stops_20190101_120000_000001",
+            aliases(&["munin_global_stops"]),
+        )
+        .unwrap();
+        assert_eq!(global_stops.doc_type, "stop");
+        assert_eq!(global_stops.dataset, None);
+
+        assert!(describe_index("munin_bob_20190101_120000_000001", vec![]).is_none());
+    }
+}
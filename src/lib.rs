@@ -29,7 +29,8 @@
 // www.navitia.io
 
 pub mod addr_reader;
-pub mod admin_geofinder;
+pub use mimir::admin_geofinder;
+pub mod dump;
 pub mod labels;
 pub mod osm_reader;
 pub mod stops;
@@ -33,7 +33,8 @@ use super::osm_utils::make_centroid;
 use super::OsmPbfReader;
 use crate::admin_geofinder::AdminGeoFinder;
 use crate::{labels, utils};
-use mimir::{rubber, Poi, PoiType};
+use mimir::store::PlaceSearch;
+use mimir::{Poi, PoiType};
 use osm_boundaries_utils::build_boundary;
 use serde::{Deserialize, Serialize};
 use serde_json;
@@ -296,11 +297,13 @@ pub fn compute_poi_weight(pois_vec: &mut [Poi]) {
     }
 }
 
-pub fn add_address(pois_vec: &mut [Poi], rubber: &mut rubber::Rubber) {
-    for poi in pois_vec {
-        poi.address = rubber
-            .get_address(&poi.coord)
-            .ok()
+pub fn add_address<S: PlaceSearch>(pois_vec: &mut [Poi], store: &mut S) {
+    // the addresses of all the pois are searched at once, by batches
+    // (the pois of a failed search have no address)
+    let coords: Vec<_> = pois_vec.iter().map(|poi| poi.coord.clone()).collect();
+    let addresses = store.get_addresses(&coords);
+    for (poi, addrs) in pois_vec.iter_mut().zip(addresses) {
+        poi.address = addrs
             .and_then(|addrs| addrs.into_iter().next())
             .map(|addr| addr.address().unwrap());
         if poi.address.is_none() {
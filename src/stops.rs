@@ -33,14 +33,15 @@ use crate::{labels, utils};
 use failure::format_err;
 use failure::{Error, ResultExt};
 use mimir;
-use mimir::rubber::{IndexSettings, Rubber, TypedIndex};
+use mimir::rubber::{get_main_type_index, IndexSettings, Rubber, ScrollSettings, TypedIndex};
+use mimir::Admin;
 use slog_scope::{info, warn};
 use std::collections::HashMap;
 use std::mem::replace;
 use std::ops::Deref;
 use std::sync::Arc;
 
-const GLOBAL_STOP_INDEX_NAME: &'static str = "munin_global_stops";
+pub use mimir::rubber::GLOBAL_STOP_INDEX_NAME;
 
 pub fn set_weights<'a, It>(stops: It, nb_stop_points: &HashMap<String, u32>)
 where
@@ -58,12 +59,11 @@ where
 
 pub fn import_stops(
     mut stops: Vec<mimir::Stop>,
-    connection_string: &str,
+    mut rubber: Rubber,
     dataset: &str,
     index_settings: IndexSettings,
 ) -> Result<(), Error> {
     info!("creation of indexes");
-    let mut rubber = Rubber::new(connection_string);
     rubber.initialize_templates()?;
 
     attach_stops_to_admins(stops.iter_mut(), &mut rubber);
@@ -84,6 +84,43 @@ pub fn import_stops(
     Ok(())
 }
 
+/// the weight of a line is its number of served stop areas, relative to the longest line
+pub fn set_line_weights(lines: &mut [mimir::Line]) {
+    let max = lines
+        .iter()
+        .map(|l| l.stop_areas.len())
+        .max()
+        .unwrap_or(1)
+        .max(1) as f64;
+    for line in lines {
+        line.weight = line.stop_areas.len() as f64 / max;
+    }
+}
+
+/// import the lines in a private index of the dataset
+///
+/// The lines are only searched when the dataset and the `public_transport:line` type
+/// are explicitly requested.
+pub fn import_lines(
+    mut lines: Vec<mimir::Line>,
+    mut rubber: Rubber,
+    dataset: &str,
+    mut index_settings: IndexSettings,
+) -> Result<(), Error> {
+    rubber.initialize_templates()?;
+
+    for line in &mut lines {
+        line.coverages.push(dataset.to_string());
+    }
+    // the lines are not attached to any administrative region
+    index_settings.publication_checks.max_no_admin_ratio = None;
+
+    info!("Importing {} lines into Mimir", lines.len());
+    let nb_lines = rubber.private_index(dataset, &index_settings, lines.into_iter())?;
+    info!("Nb of indexed lines: {}", nb_lines);
+    Ok(())
+}
+
 fn attach_stop(stop: &mut mimir::Stop, admins: Vec<Arc<mimir::Admin>>) {
     let admins_iter = admins.iter().map(|a| a.deref());
     let country_codes = utils::find_country_codes(admins_iter.clone());
@@ -104,14 +141,21 @@ fn attach_stops_to_admins<'a, It: Iterator<Item = &'a mut mimir::Stop>>(
     stops: It,
     rubber: &mut Rubber,
 ) {
-    let admins = rubber.get_all_admins().unwrap_or_else(|_| {
-        warn!("Administratives regions not found in elasticsearch db");
-        vec![]
-    });
-
-    info!("{} administrative regions loaded from mimir", admins.len());
+    // the admins are streamed from elasticsearch into the geofinder
+    let mut nb_admins = 0;
+    let admins_geofinder = rubber
+        .scroll_objects::<Admin>(&get_main_type_index::<Admin>(), &ScrollSettings::default())
+        .and_then(|admins| {
+            admins
+                .inspect(|_| nb_admins += 1)
+                .collect::<Result<AdminGeoFinder, _>>()
+        })
+        .unwrap_or_else(|_| {
+            warn!("Administratives regions not found in elasticsearch db");
+            AdminGeoFinder::default()
+        });
 
-    let admins_geofinder = admins.into_iter().collect::<AdminGeoFinder>();
+    info!("{} administrative regions loaded from mimir", nb_admins);
 
     let mut nb_unmatched = 0u32;
     let mut nb_matched = 0u32;
@@ -144,37 +188,25 @@ fn merge_collection<T: Ord>(target: &mut Vec<T>, source: Vec<T>) {
         .collect();
 }
 
-/// merge the stops from all the different indexes
+/// merge a stop in the stops from all the different indexes
 /// for the moment the merge is very simple and uses only the ID
 /// (and we take the data from the first stop inserted)
-fn merge_stops<It: IntoIterator<Item = mimir::Stop>>(
-    stops: It,
-) -> impl Iterator<Item = mimir::Stop> {
-    let mut stops_by_id = HashMap::<String, mimir::Stop>::new();
-    for mut stop in stops.into_iter() {
-        let cov = replace(&mut stop.coverages, vec![]);
-        let codes = replace(&mut stop.codes, vec![]);
-        let physical_modes = replace(&mut stop.physical_modes, vec![]);
-        let commercial_modes = replace(&mut stop.commercial_modes, vec![]);
-        let properties = replace(&mut stop.properties, vec![]);
-        let feed_publishers = replace(&mut stop.feed_publishers, vec![]);
-
-        let stop_in_map = stops_by_id.entry(stop.id.clone()).or_insert(stop);
-
-        merge_collection(&mut stop_in_map.codes, codes);
-        merge_collection(&mut stop_in_map.physical_modes, physical_modes);
-        merge_collection(&mut stop_in_map.commercial_modes, commercial_modes);
-        merge_collection(&mut stop_in_map.coverages, cov);
-        merge_collection(&mut stop_in_map.properties, properties);
-        merge_collection(&mut stop_in_map.feed_publishers, feed_publishers);
-    }
-    stops_by_id.into_iter().map(|(_, v)| v)
-}
+fn merge_stop(stops_by_id: &mut HashMap<String, mimir::Stop>, mut stop: mimir::Stop) {
+    let cov = replace(&mut stop.coverages, vec![]);
+    let codes = replace(&mut stop.codes, vec![]);
+    let physical_modes = replace(&mut stop.physical_modes, vec![]);
+    let commercial_modes = replace(&mut stop.commercial_modes, vec![]);
+    let properties = replace(&mut stop.properties, vec![]);
+    let feed_publishers = replace(&mut stop.feed_publishers, vec![]);
+
+    let stop_in_map = stops_by_id.entry(stop.id.clone()).or_insert(stop);
 
-fn get_all_stops(rubber: &mut Rubber, index: String) -> Result<Vec<mimir::Stop>, Error> {
-    rubber
-        .get_all_objects_from_index(&index)
-        .map_err(|e| format_err!("Getting all stops {}", e.to_string()))
+    merge_collection(&mut stop_in_map.codes, codes);
+    merge_collection(&mut stop_in_map.physical_modes, physical_modes);
+    merge_collection(&mut stop_in_map.commercial_modes, commercial_modes);
+    merge_collection(&mut stop_in_map.coverages, cov);
+    merge_collection(&mut stop_in_map.properties, properties);
+    merge_collection(&mut stop_in_map.feed_publishers, feed_publishers);
 }
 
 fn update_global_stop_index<'a, It: Iterator<Item = &'a mimir::Stop>>(
@@ -190,14 +222,22 @@ fn update_global_stop_index<'a, It: Iterator<Item = &'a mimir::Stop>>(
         .filter(|&(_, ref aliases)| !aliases.contains(&dataset_index))
         .map(|(index, _)| index);
 
-    let all_es_stops = stops_indexes
-        .map(|index| get_all_stops(rubber, index))
-        .collect::<Result<Vec<_>, _>>()?
-        .into_iter()
-        .flat_map(|stops| stops.into_iter())
-        .chain(stops.into_iter().cloned());
-
-    let all_merged_stops = merge_stops(all_es_stops);
+    // the stops of the other datasets are streamed from elasticsearch,
+    // only the merged stops are kept in memory
+    let mut stops_by_id = HashMap::new();
+    for index in stops_indexes {
+        let es_stops = rubber
+            .scroll_objects::<mimir::Stop>(&index, &ScrollSettings::default())
+            .map_err(|e| format_err!("Getting all stops {}", e.to_string()))?;
+        for stop in es_stops {
+            let stop = stop.map_err(|e| format_err!("Getting all stops {}", e.to_string()))?;
+            merge_stop(&mut stops_by_id, stop);
+        }
+    }
+    for stop in stops {
+        merge_stop(&mut stops_by_id, stop.clone());
+    }
+    let all_merged_stops = stops_by_id.into_iter().map(|(_, stop)| stop);
     let es_index_name = mimir::rubber::get_date_index_name(GLOBAL_STOP_INDEX_NAME);
 
     rubber.create_index(&es_index_name, &index_settings)?;
@@ -61,10 +61,50 @@ pub fn bragi_bano_test(es_wrapper: crate::ElasticSearchWrapper<'_>) {
 }
 
 fn status_test(bragi: &mut BragiHandler) {
+    let status = bragi.get_json("/status");
+    assert_eq!(status.pointer("/status"), Some(&json!("good")));
+    assert!(status
+        .pointer("/es_health")
+        .and_then(|h| h.as_str())
+        .is_some());
+
+    // the published indexes are listed
+    let indexes = status
+        .pointer("/indexes")
+        .and_then(|i| i.as_array())
+        .unwrap();
+    assert_eq!(indexes.len(), 1);
+    assert_eq!(indexes[0].pointer("/doc_type"), Some(&json!("addr")));
+    assert_eq!(indexes[0].pointer("/dataset"), Some(&json!("fr")));
+    assert_eq!(indexes[0].pointer("/public"), Some(&json!(true)));
+    assert_eq!(
+        indexes[0].pointer("/nb_documents"),
+        status.pointer("/datasets/0/nb_documents")
+    );
+
+    // the status lists the published datasets, with the metadata of their import
+    let datasets = status
+        .pointer("/datasets")
+        .and_then(|d| d.as_array())
+        .unwrap();
+    assert_eq!(datasets.len(), 1);
+    let addr = &datasets[0];
+    assert_eq!(addr.pointer("/dataset"), Some(&json!("fr")));
+    assert_eq!(addr.pointer("/doc_type"), Some(&json!("addr")));
+    assert!(
+        addr.pointer("/nb_documents")
+            .and_then(|n| n.as_u64())
+            .unwrap()
+            > 0
+    );
+    assert_eq!(addr.pointer("/nb_failed_documents"), Some(&json!(0)));
     assert_eq!(
-        bragi.get_json("/status").pointer("/status"),
-        Some(&json!("good"))
+        addr.pointer("/import/sources/0/path"),
+        Some(&json!("./tests/fixtures/sample-bano.csv"))
     );
+    let checksum = addr.pointer("/import/sources/0/checksum").unwrap();
+    assert_eq!(checksum.as_str().map(|c| c.len()), Some(40));
+    assert!(addr.pointer("/published_at").is_some());
 }
 
 fn simple_bano_autocomplete_test(bragi: &mut BragiHandler) {
@@ -244,4 +284,43 @@ fn reverse_bano_test(bragi: &mut BragiHandler) {
         get_values(&res, "label"),
         vec!["2 Rue des Pins (Beauzelle)"]
     );
+
+    // several places within the radius, the nearest first
+    let json = bragi.get_json("/reverse?lon=2.37716&lat=48.8468&radius=100&limit=5");
+    let features = json
+        .pointer("/features")
+        .and_then(|f| f.as_array())
+        .unwrap();
+    assert_eq!(features.len(), 5);
+    assert_eq!(
+        features[0].pointer("/properties/geocoding/label"),
+        Some(&json!("20 Rue Hector Malot (Paris)"))
+    );
+    let distances: Vec<u64> = features
+        .iter()
+        .map(|f| f.get("distance").and_then(|d| d.as_u64()).unwrap())
+        .collect();
+    assert!(distances.windows(2).all(|d| d[0] <= d[1]));
+    assert!(distances.iter().all(|d| *d <= 100));
+
+    // nothing within the radius
+    let res = bragi.get("/reverse?lon=2.37716&lat=48.8568&radius=10&limit=5");
+    assert!(res.is_empty());
+
+    for params in &[
+        "lon=2.37716&lat=48.8468&radius=-1",
+        "lon=2.37716&lat=48.8468&radius=NaN",
+        "lon=2.37716&lat=48.8468&radius=inf",
+        "lon=NaN&lat=48.8468",
+        "lon=2.37716&lat=NaN",
+        "lon=2.37716&lat=48.8468&limit=101",
+    ] {
+        let (status, _) = bragi.get_unchecked_json(&format!("/reverse?{}", params));
+        assert_eq!(
+            status,
+            actix_web::http::StatusCode::BAD_REQUEST,
+            "{}",
+            params
+        );
+    }
 }
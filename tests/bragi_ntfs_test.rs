@@ -49,6 +49,7 @@ pub fn bragi_ntfs_test(es_wrapper: crate::ElasticSearchWrapper<'_>) {
     );
 
     gare_de_lyon(&mut bragi);
+    rer_a_line(&mut bragi);
 
     let ntfs2mimir = out_dir.join("../../../ntfs2mimir").display().to_string();
     crate::launch_and_assert(
@@ -172,6 +173,52 @@ fn gare_de_lyon(bragi: &mut BragiHandler) {
     );
 }
 
+fn rer_a_line(bragi: &mut BragiHandler) {
+    // the lines are only returned when explicitly asked for
+    let response = bragi.get("/autocomplete?q=RER A&pt_dataset[]=dataset1");
+    assert!(response
+        .iter()
+        .all(|p| get_value(p, "type") != "public_transport:line"));
+
+    let response =
+        bragi.get("/autocomplete?q=RER A&type[]=public_transport:line&pt_dataset[]=dataset1");
+    assert!(response
+        .iter()
+        .all(|p| get_value(p, "type") == "public_transport:line"));
+    let line = response.first().unwrap();
+    assert_eq!(get_value(line, "id"), "line:RERA");
+    assert_eq!(get_value(line, "name"), "RER A");
+    assert_eq!(get_value(line, "label"), "RER A (The Great Network)");
+    assert_eq!(
+        line.get("network").unwrap(),
+        &json!({ "id": "network:TGN", "name": "The Great Network" })
+    );
+    assert_eq!(
+        line.get("commercial_modes").unwrap(),
+        &json!([
+            {"id": "commercial_mode:RER", "name": "Réseau Express Régional (RER)"}
+        ])
+    );
+    assert_eq!(
+        line.get("stop_areas").unwrap(),
+        &json!([
+            "stop_area:GDL",
+            "stop_area:NAT",
+            "stop_area:CDG",
+            "stop_area:DEF"
+        ])
+    );
+    assert_eq!(
+        line.get("bbox").unwrap(),
+        &json!([2.238964, 48.844746, 2.396497, 48.891737])
+    );
+
+    // the lines of the other datasets are not returned
+    let response =
+        bragi.get("/autocomplete?q=RER A&type[]=public_transport:line&pt_dataset[]=dataset2");
+    assert!(response.is_empty());
+}
+
 fn gare_de_lyon_with_two_datasets(bragi: &mut BragiHandler) {
     // with this query we should find only one response, a stop
     let response =
@@ -86,6 +86,7 @@ pub fn bragi_poi_test(es_wrapper: crate::ElasticSearchWrapper<'_>) {
     poi_from_osm_with_address_addr_test(&mut bragi);
     poi_filter_poi_type_test(&mut bragi);
     poi_filter_error_message_test(&mut bragi);
+    poi_reverse_test(&mut bragi);
 }
 
 pub fn bragi_private_poi_test(es_wrapper: crate::ElasticSearchWrapper<'_>) {
@@ -288,6 +289,30 @@ fn poi_from_osm_test(bragi: &mut BragiHandler) {
     assert!(!get_values(&geocodings, "label").contains(&"ENSE3 site Ampère",));
 }
 
+fn poi_reverse_test(bragi: &mut BragiHandler) {
+    let json = bragi.get_json("/autocomplete?q=77000 Hôtel de Ville (Melun)");
+    let coord = json
+        .pointer("/features/0/geometry/coordinates")
+        .and_then(|c| c.as_array())
+        .unwrap();
+    let (lon, lat) = (coord[0].as_f64().unwrap(), coord[1].as_f64().unwrap());
+
+    let query = format!(
+        "/reverse?lon={}&lat={}&type[]=poi&radius=10&limit=5",
+        lon, lat
+    );
+    let res = bragi.get(&query);
+    assert!(!res.is_empty());
+    assert!(get_types(&res).iter().all(|t| *t == Poi::doc_type()));
+    assert_eq!(get_value(&res[0], "id"), "poi:osm:way:112361498");
+    let json = bragi.get_json(&query);
+    assert_eq!(json.pointer("/features/0/distance"), Some(&json!(0)));
+
+    // by default only the addresses and the streets are returned
+    let res = bragi.get(&format!("/reverse?lon={}&lat={}&limit=5", lon, lat));
+    assert_eq!(count_types(&get_types(&res), Poi::doc_type()), 0);
+}
+
 fn poi_misspelt_one_word_admin_test(bragi: &mut BragiHandler) {
     // with this search we should be able to find a poi called "Melun"
     let geocodings = bragi.get("/autocomplete?q=Melun");
@@ -377,6 +402,7 @@ pub fn test_i18n_poi(mut es: crate::ElasticSearchWrapper<'_>) {
     let index_settings = mimir::rubber::IndexSettings {
         nb_shards: 2,
         nb_replicas: 1,
+        ..Default::default()
     };
     // we index the poi above
     let _result = es
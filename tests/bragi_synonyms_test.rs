@@ -41,7 +41,7 @@ pub fn bragi_synonyms_test(es_wrapper: crate::ElasticSearchWrapper<'_>) {
     // the current dataset are thus (load order matters):
     // - osm_fixture.osm.pbf
     // - bano-three_cities
-    // - stops.txt
+    // - stops.txt (with its own synonyms)
     // ******************************************
     let osm2mimir = out_dir.join("../../../osm2mimir").display().to_string();
     crate::launch_and_assert(
@@ -73,12 +73,14 @@ pub fn bragi_synonyms_test(es_wrapper: crate::ElasticSearchWrapper<'_>) {
         &[
             "--input=./tests/fixtures/stops.txt".into(),
             "--dataset=dataset1".into(),
+            "--synonyms=./tests/fixtures/synonyms.txt".into(),
             format!("--connection-string={}", es_wrapper.host()),
         ],
         &es_wrapper,
     );
 
     synonyms_test(&mut bragi);
+    synonyms_list_test(&mut bragi);
 }
 
 fn synonyms_test(bragi: &mut BragiHandler) {
@@ -94,3 +96,33 @@ fn synonyms_test(bragi: &mut BragiHandler) {
         .iter()
         .all(|r| r.contains("Hôtel de Ville")));
 }
+
+fn synonyms_list_test(bragi: &mut BragiHandler) {
+    let response = bragi.get_json("/synonyms");
+    let synonyms = response.as_object().unwrap();
+    let index_synonyms = |prefix: &str| -> Vec<&str> {
+        let (_, synonyms) = synonyms
+            .iter()
+            .find(|(index, _)| index.starts_with(prefix))
+            .unwrap_or_else(|| panic!("no synonyms for {}", prefix));
+        synonyms
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s.as_str().unwrap())
+            .collect()
+    };
+
+    // the stops have been imported with the synonyms of the fixture file
+    assert_eq!(
+        index_synonyms("munin_stop_dataset1_"),
+        vec!["gare,station", "hotel de ville,mairie"]
+    );
+    // the other indexes use the default synonyms
+    assert!(index_synonyms("munin_addr_")
+        .iter()
+        .any(|s| s == &"hotel de ville,mairie"));
+    assert!(!index_synonyms("munin_addr_")
+        .iter()
+        .any(|s| s == &"gare,station"));
+}
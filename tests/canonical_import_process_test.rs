@@ -93,6 +93,12 @@ pub fn canonical_import_process_test(es_wrapper: crate::ElasticSearchWrapper<'_>
     valid_timeout_test(&mut bragi);
     filter_zone_type_test(&mut bragi);
     zone_filter_error_message_test(&mut bragi);
+    reverse_zones_test(&mut bragi, &es_wrapper);
+    structured_search_test(&mut bragi);
+    query_parser_test(&mut bragi);
+    batch_test(&mut bragi);
+    ranking_profile_test(&mut bragi, &es_wrapper);
+    relevance_test(&mut bragi, &es_wrapper);
 }
 
 fn melun_test(bragi: &mut BragiHandler) {
@@ -247,9 +253,13 @@ fn lang_test(bragi: &mut BragiHandler) {
 pub fn bragi_invalid_es_test(_es_wrapper: crate::ElasticSearchWrapper<'_>) {
     let mut bragi = BragiHandler::new(format!("http://invalid_es_url/munin"));
 
-    // the status does not check the ES connexion, so for the status all is good
-    let (status, _) = bragi.raw_get("/status");
-    assert_eq!(status, actix_web::http::StatusCode::OK);
+    // elasticsearch is not reachable, so the status is a 503
+    let (status, body) = bragi.get_unchecked_json("/status");
+    assert_eq!(status, actix_web::http::StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(
+        body.pointer("/status"),
+        Some(&json!("elasticsearch unreachable"))
+    );
 
     // the autocomplete gives a 503
     let r = bragi.get_unchecked_json("/autocomplete?q=toto");
@@ -407,3 +417,278 @@ fn zone_filter_error_message_test(bragi: &mut BragiHandler) {
         )
     );
 }
+
+fn reverse_zones_test(bragi: &mut BragiHandler, es_wrapper: &crate::ElasticSearchWrapper<'_>) {
+    // the zones are not loaded by default
+    let (status, _) = bragi.get_unchecked_json("/reverse/zones?lat=48.54&lon=2.655");
+    assert_eq!(status, actix_web::http::StatusCode::NOT_IMPLEMENTED);
+
+    let mut bragi = BragiHandler::from_args(bragi::Args {
+        connection_string: es_wrapper.host(),
+        load_zones: true,
+        ..Default::default()
+    });
+    // the admin hierarchy of a point in Melun, from the city to the country
+    let zones = bragi.get("/reverse/zones?lat=48.54&lon=2.655");
+    assert_eq!(zones.len(), 3, "{:?}", zones);
+    assert_eq!(get_value(&zones[0], "id"), "admin:osm:relation:80071");
+    assert_eq!(get_value(&zones[0], "zone_type"), "city");
+    assert_eq!(get_value(&zones[2], "zone_type"), "country");
+
+    // no zone in the middle of the ocean
+    let zones = bragi.get("/reverse/zones?lat=40.&lon=-30.");
+    assert!(zones.is_empty());
+}
+
+fn structured_search_test(bragi: &mut BragiHandler) {
+    // with a house number, only the addresses are searched
+    let addrs =
+        bragi.get("/search/structured?housenumber=2&street=rue de la reine blanche&city=Melun");
+    assert_eq!(addrs.len(), 1, "{:?}", addrs);
+    assert_eq!(get_value(&addrs[0], "type"), "house");
+    assert_eq!(
+        get_value(&addrs[0], "label"),
+        "2 Rue de la Reine Blanche (Melun)"
+    );
+    let addrs = bragi.get(
+        "/search/structured?housenumber=2&street=Rue de la Reine Blanche&postcode=77288&country=France",
+    );
+    assert_eq!(
+        get_value(&addrs[0], "label"),
+        "2 Rue de la Reine Blanche (Melun)"
+    );
+    // each part must match its own field
+    assert!(bragi
+        .get("/search/structured?housenumber=2&street=rue de la reine blanche&city=Paris")
+        .is_empty());
+    assert!(bragi
+        .get("/search/structured?housenumber=2&street=Melun&city=rue de la reine blanche")
+        .is_empty());
+
+    // without house number, the streets
+    let streets = bragi.get("/search/structured?street=rue de la reine blanche&city=Melun");
+    assert!(!streets.is_empty());
+    assert!(streets.iter().all(|s| get_value(s, "type") == "street"));
+    assert_eq!(get_value(&streets[0], "name"), "Rue de la Reine Blanche");
+
+    // without street, the cities
+    let cities = bragi.get("/search/structured?city=Melun&country=France");
+    assert_eq!(cities.len(), 1, "{:?}", cities);
+    assert_eq!(get_value(&cities[0], "id"), "admin:osm:relation:80071");
+    let cities = bragi.get("/search/structured?postcode=77003");
+    assert_eq!(get_value(&cities[0], "id"), "admin:osm:relation:80071");
+    assert!(bragi
+        .get("/search/structured?city=Melun&country=Espagne")
+        .is_empty());
+
+    // and the countries
+    let countries = bragi.get("/search/structured?country=France");
+    assert_eq!(countries.len(), 1, "{:?}", countries);
+    assert_eq!(get_value(&countries[0], "zone_type"), "country");
+
+    assert_eq!(
+        bragi.get_unchecked_json("/search/structured?housenumber=2&city=Melun"),
+        (
+            actix_web::http::StatusCode::BAD_REQUEST,
+            json!({
+                "short": "validation error",
+                "long": "Invalid parameter: housenumber parameter requires to have a 'street'",
+            })
+        )
+    );
+    let (status, _) = bragi.get_unchecked_json("/search/structured?city=");
+    assert_eq!(status, actix_web::http::StatusCode::BAD_REQUEST);
+    let (status, _) = bragi.get_unchecked_json("/search/structured?city=Melun&limit=101");
+    assert_eq!(status, actix_web::http::StatusCode::BAD_REQUEST);
+}
+
+fn query_parser_test(bragi: &mut BragiHandler) {
+    // the parts of the address recognized in the query are shown with `_debug`
+    let json = bragi.get_json("/autocomplete?q=2 Rue de la Reine Blanche, Melun&_debug=true");
+    assert_eq!(
+        json["geocoding"]["parsed_query"],
+        json!({"house_number": "2", "city": "Melun"})
+    );
+    assert_eq!(
+        json["features"][0]["properties"]["geocoding"]["label"],
+        "2 Rue de la Reine Blanche (Melun)"
+    );
+    let json = bragi.get_json("/autocomplete?q=2 Rue de la Reine Blanche");
+    assert!(json["geocoding"].get("parsed_query").is_none());
+
+    // the house number at the end of the query
+    let res = bragi.get("/autocomplete?q=Rue de la Reine Blanche 2");
+    assert_eq!(
+        get_value(&res[0], "label"),
+        "2 Rue de la Reine Blanche (Melun)"
+    );
+
+    // the postcode and the city filter the places
+    let res = bragi.get("/autocomplete?q=2 Rue de la Reine Blanche 77288 Melun");
+    assert_eq!(
+        get_value(&res[0], "label"),
+        "2 Rue de la Reine Blanche (Melun)"
+    );
+    let res = bragi.get("/autocomplete?q=2 Rue de la Reine Blanche 75001 Melun");
+    assert!(!get_values(&res, "type").contains(&"house"), "{:?}", res);
+    let res = bragi.get("/autocomplete?q=Rue de la Reine Blanche, Paris");
+    let types = get_values(&res, "type");
+    assert!(
+        !types.contains(&"house") && !types.contains(&"street"),
+        "{:?}",
+        res
+    );
+
+    // a comma after the house number is not followed by a city
+    let json = bragi.get_json("/autocomplete?q=2, Rue de la Reine Blanche&_debug=true");
+    assert_eq!(
+        json["geocoding"]["parsed_query"],
+        json!({"house_number": "2"})
+    );
+    assert_eq!(
+        json["features"][0]["properties"]["geocoding"]["label"],
+        "2 Rue de la Reine Blanche (Melun)"
+    );
+}
+
+fn batch_test(bragi: &mut BragiHandler) {
+    // a csv, the query being built from several columns
+    let results = bragi.post_batch(
+        "/batch/search?column[]=street&column[]=postcode&column[]=city",
+        "text/csv",
+        "id,street,postcode,city\n\
+         1,2 rue de la reine blanche,77288,Melun\n\
+         2,,,\n"
+            .to_string(),
+    );
+    assert_eq!(results.len(), 2, "{:?}", results);
+    assert_eq!(results[0]["row"], 1);
+    assert_eq!(results[0]["status"], "matched");
+    assert_eq!(results[0]["score"], 1.);
+    assert_eq!(
+        results[0]["feature"]["properties"]["geocoding"]["label"],
+        "2 Rue de la Reine Blanche (Melun)"
+    );
+    assert_eq!(results[1]["row"], 2);
+    assert_eq!(results[1]["status"], "error");
+
+    // json lines, with the default columns
+    let results = bragi.post_batch(
+        "/batch/reverse",
+        "application/x-ndjson",
+        r#"{"lat": 48.53685, "lon": 2.65801}
+{"lat": 40.0, "lon": -30.0}
+{"lat": "a", "lon": 2.6}
+"#
+        .to_string(),
+    );
+    assert_eq!(results.len(), 3, "{:?}", results);
+    assert_eq!(results[0]["status"], "matched");
+    assert_eq!(
+        results[0]["feature"]["properties"]["geocoding"]["label"],
+        "2 Rue de la Reine Blanche (Melun)"
+    );
+    assert!(results[0]["score"].as_f64().unwrap() > 0.99);
+    // nothing in the middle of the ocean
+    assert_eq!(results[1]["status"], "not_found");
+    assert_eq!(results[2]["status"], "error");
+    assert_eq!(results[2]["error"], r#"invalid lat "a""#);
+
+    // too many rows
+    let (status, _) = bragi.raw_post_body(
+        "/batch/search",
+        "application/x-ndjson",
+        "{\"q\": \"melun\"}\n".repeat(50_001),
+    );
+    assert_eq!(status, actix_web::http::StatusCode::BAD_REQUEST);
+}
+
+fn ranking_profile_test(bragi: &mut BragiHandler, es_wrapper: &crate::ElasticSearchWrapper<'_>) {
+    // without any profiles file, only the default profile can be used
+    let (status, error) = bragi.get_unchecked_json("/autocomplete?q=Melun&_profile=pois_first");
+    assert_eq!(status, actix_web::http::StatusCode::BAD_REQUEST);
+    assert_eq!(error["long"], "unknown ranking profile pois_first");
+
+    let profiles = Path::new(env!("OUT_DIR")).join("ranking_profiles.json");
+    std::fs::write(&profiles, r#"{"pois_first": {"types": {"poi": 1000}}}"#).unwrap();
+    let mut bragi = BragiHandler::from_args(bragi::Args {
+        connection_string: es_wrapper.host(),
+        ranking_profiles: Some(profiles),
+        ..Default::default()
+    });
+
+    // the default profile has not changed, the city is first
+    let all_melun = bragi.get("/autocomplete?q=Melun");
+    assert_eq!(get_value(all_melun.first().unwrap(), "zone_type"), "city");
+
+    // with the pois_first profile, a poi is first
+    let all_melun = bragi.get("/autocomplete?q=Melun&_profile=pois_first");
+    assert_eq!(get_value(all_melun.first().unwrap(), "type"), "poi");
+}
+
+fn relevance_test(bragi: &mut BragiHandler, es_wrapper: &crate::ElasticSearchWrapper<'_>) {
+    use tools::relevance::{evaluate, read_queries};
+    let queries_file = Path::new(env!("OUT_DIR")).join("relevance_queries.jsonl");
+    std::fs::write(
+        &queries_file,
+        r#"# the city must be first, and it is not found among the pois
+{"q": "Melun", "expected": [{"id": "admin:osm:relation:80071", "rank": 1}]}
+{"q": "Melun", "params": "type[]=poi", "expected": [{"id": "admin:osm:relation:80071"}]}
+"#,
+    )
+    .unwrap();
+    let queries = read_queries(&queries_file).unwrap();
+    assert_eq!(queries.len(), 2);
+
+    let baseline = evaluate(bragi, &queries, 5);
+    assert!(baseline.failures().next().is_none());
+    assert_eq!(baseline.queries[0].reciprocal_rank, 1.);
+    // no admin is returned when searching the pois
+    assert_eq!(baseline.queries[1].precision, 0.);
+    assert_eq!(baseline.mrr, 0.5);
+
+    // the same results, without any regression
+    let regressed = evaluate(bragi, &queries, 5)
+        .compare(&baseline, 0.)
+        .regressed;
+    assert!(!regressed);
+
+    // with the pois first, the city is no longer first
+    let profiles = Path::new(env!("OUT_DIR")).join("relevance_profiles.json");
+    std::fs::write(&profiles, r#"{"default": {"types": {"poi": 1000}}}"#).unwrap();
+    let mut pois_first = BragiHandler::from_args(bragi::Args {
+        connection_string: es_wrapper.host(),
+        ranking_profiles: Some(profiles),
+        ..Default::default()
+    });
+    let report = evaluate(&mut pois_first, &queries, 5);
+    assert_eq!(
+        report.failures().map(|f| &f.key).collect::<Vec<_>>(),
+        vec!["Melun"]
+    );
+    let comparison = report.compare(&baseline, 0.);
+    assert!(comparison.regressed);
+    assert!(comparison.diffs.iter().any(|d| d.is_worse()));
+}
+
+pub fn bragi_failover_test(es_wrapper: crate::ElasticSearchWrapper<'_>) {
+    let out_dir = Path::new(env!("OUT_DIR"));
+    let bano2mimir = out_dir.join("../../../bano2mimir").display().to_string();
+    crate::launch_and_assert(
+        &bano2mimir,
+        &[
+            "--input=./tests/fixtures/bano-three_cities.csv".into(),
+            format!("--connection-string={}", es_wrapper.host()),
+        ],
+        &es_wrapper,
+    );
+
+    // the first node of the cluster is not reachable, the searches are done on the second one
+    let mut bragi = BragiHandler::new(format!("http://invalid_es_url,{}", es_wrapper.host()));
+    let (status, _) = bragi.raw_get("/status");
+    assert_eq!(status, actix_web::http::StatusCode::OK);
+    for _ in 0..3 {
+        let res = bragi.get("/autocomplete?q=2 Rue de la Reine Blanche");
+        assert!(!res.is_empty());
+    }
+}
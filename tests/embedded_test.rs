@@ -0,0 +1,105 @@
+// Copyright © 2017, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use super::get_values;
+use super::BragiHandler;
+use std::path::Path;
+
+/// import bano in an embedded index and query it with bragi, without elasticsearch
+pub fn bragi_embedded_bano_test() {
+    let dir = std::env::temp_dir().join("mimir_embedded_bano_test");
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    let bano2mimir = Path::new(env!("OUT_DIR"))
+        .join("../../../bano2mimir")
+        .display()
+        .to_string();
+    let status = std::process::Command::new(&bano2mimir)
+        .args(&[
+            "--input=./tests/fixtures/sample-bano.csv".to_string(),
+            format!("--embedded-index={}", dir.display()),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success(), "`{}` failed {}", bano2mimir, &status);
+
+    let mut bragi = BragiHandler::new_embedded(&dir);
+
+    let res = bragi.get("/autocomplete?q=15 Rue Hector Malot (Paris)");
+    assert_eq!(
+        get_values(&res, "label").first(),
+        Some(&"15 Rue Hector Malot (Paris)")
+    );
+
+    // prefix search
+    let all_20 = bragi.get("/autocomplete?q=20 rue hect mal");
+    assert_eq!(all_20.len(), 2);
+
+    // the proximity is used to sort the results
+    let all_20 = bragi.get("/autocomplete?q=20 rue hector malot&lat=50.2&lon=2.0");
+    assert_eq!(
+        get_values(&all_20, "label"),
+        vec![
+            "20 Rue Hector Malot (Trifouilli-les-Oies)",
+            "20 Rue Hector Malot (Paris)",
+        ]
+    );
+
+    // fuzzy search
+    let res = bragi.get("/autocomplete?q=20 rue hectorr malot");
+    assert!(get_values(&res, "label").contains(&"20 Rue Hector Malot (Paris)"));
+
+    let res = bragi.get("/reverse?lon=2.37716&lat=48.8468");
+    assert_eq!(
+        get_values(&res, "label"),
+        vec!["20 Rue Hector Malot (Paris)"]
+    );
+
+    let id = res[0].get("id").and_then(|id| id.as_str()).unwrap();
+    let res = bragi.get(&format!("/features/{}", id));
+    assert_eq!(
+        get_values(&res, "label"),
+        vec!["20 Rue Hector Malot (Paris)"]
+    );
+
+    // the index is persisted: a new import replaces it
+    let status = std::process::Command::new(&bano2mimir)
+        .args(&[
+            "--input=./tests/fixtures/sample-bano.csv".to_string(),
+            format!("--embedded-index={}", dir.display()),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success(), "`{}` failed {}", bano2mimir, &status);
+    let mut bragi = BragiHandler::new_embedded(&dir);
+    let all_20 = bragi.get("/autocomplete?q=20 rue hect mal");
+    assert_eq!(all_20.len(), 2);
+}
@@ -0,0 +1,145 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+// the aliases of all the indexes (the names of the indexes are not kept by a restore)
+fn get_all_aliases(es_wrapper: &crate::ElasticSearchWrapper<'_>) -> BTreeSet<Vec<String>> {
+    let mut res = reqwest::get(&format!("{host}/_aliases", host = es_wrapper.host())).unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    let json: serde_json::Value = res.json().unwrap();
+    json.as_object()
+        .unwrap()
+        .values()
+        .map(|index| {
+            let mut aliases: Vec<String> = index["aliases"]
+                .as_object()
+                .map(|a| a.keys().cloned().collect())
+                .unwrap_or_default();
+            aliases.sort();
+            aliases
+        })
+        .collect()
+}
+
+/// dump some indexes, clean elasticsearch, and restore them
+/// we should find the same documents with the same aliases
+pub fn mimir_dump_restore_test(mut es_wrapper: crate::ElasticSearchWrapper<'_>) {
+    let bin = |name: &str| {
+        Path::new(env!("OUT_DIR"))
+            .join("../../../")
+            .join(name)
+            .display()
+            .to_string()
+    };
+    let dump_dir = std::env::temp_dir().join("mimir_dump_restore_test");
+    if dump_dir.exists() {
+        std::fs::remove_dir_all(&dump_dir).unwrap();
+    }
+
+    crate::launch_and_assert(
+        &bin("bano2mimir"),
+        &[
+            "--input=./tests/fixtures/sample-bano.csv".into(),
+            format!("--connection-string={}", es_wrapper.host()),
+        ],
+        &es_wrapper,
+    );
+    crate::launch_and_assert(
+        &bin("stops2mimir"),
+        &[
+            "--input=./tests/fixtures/stops.txt".into(),
+            format!("--connection-string={}", es_wrapper.host()),
+            "--dataset=dataset1".into(),
+        ],
+        &es_wrapper,
+    );
+    let aliases = get_all_aliases(&es_wrapper);
+    assert_eq!(aliases.len(), 3);
+    let nb_addrs = es_wrapper.count("munin_addr", "*");
+    let nb_stops = es_wrapper.count("munin_stop", "*");
+    let nb_global_stops = es_wrapper.count("munin_global_stops", "*");
+    assert_eq!(nb_addrs, 34);
+    assert_eq!(nb_stops, 6);
+
+    crate::launch_and_assert(
+        &bin("mimir_dump"),
+        &[
+            format!("--output={}", dump_dir.display()),
+            format!("--connection-string={}", es_wrapper.host()),
+        ],
+        &es_wrapper,
+    );
+    let manifest = mimirsbrunn::dump::Manifest::read(&dump_dir).unwrap();
+    assert_eq!(manifest.indexes.len(), 3);
+    assert!(manifest
+        .indexes
+        .iter()
+        .all(|i| dump_dir.join(&i.file).exists()));
+    let addr_metadata = manifest
+        .indexes
+        .iter()
+        .find(|i| i.doc_type == "addr")
+        .and_then(|i| i.metadata.clone())
+        .unwrap();
+
+    // we remove everything before the restore
+    es_wrapper.init();
+    assert!(get_all_aliases(&es_wrapper).is_empty());
+
+    crate::launch_and_assert(
+        &bin("mimir_restore"),
+        &[
+            format!("--input={}", dump_dir.display()),
+            format!("--connection-string={}", es_wrapper.host()),
+        ],
+        &es_wrapper,
+    );
+    assert_eq!(get_all_aliases(&es_wrapper), aliases);
+    assert_eq!(es_wrapper.count("munin_addr", "*"), nb_addrs);
+    assert_eq!(es_wrapper.count("munin_stop", "*"), nb_stops);
+    assert_eq!(es_wrapper.count("munin_global_stops", "*"), nb_global_stops);
+
+    // the restored index keeps the metadata of its import by bano2mimir
+    let rubber = mimir::rubber::Rubber::new(&es_wrapper.host());
+    let metadata = rubber.get_index_metadata("munin_addr").unwrap();
+    assert_eq!(metadata.len(), 1);
+    let (index, metadata) = metadata.into_iter().next().unwrap();
+    assert_eq!(metadata.index, index);
+    assert_eq!(metadata.import, addr_metadata.import);
+    assert_eq!(metadata.started_at, addr_metadata.started_at);
+
+    let res: Vec<_> = es_wrapper
+        .search_and_filter("label:République", |_| true)
+        .collect();
+    assert_eq!(res.len(), 1);
+    assert!(res[0].is_stop());
+}
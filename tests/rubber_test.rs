@@ -81,6 +81,7 @@ pub fn rubber_zero_downtime_test(mut es: crate::ElasticSearchWrapper<'_>) {
     let index_settings = IndexSettings {
         nb_shards: 2,
         nb_replicas: 1,
+        ..Default::default()
     };
     let result = es
         .rubber
@@ -114,6 +115,7 @@ pub fn rubber_zero_downtime_test(mut es: crate::ElasticSearchWrapper<'_>) {
     let index_settings = IndexSettings {
         nb_shards: 2,
         nb_replicas: 1,
+        ..Default::default()
     };
     let result = rubber.public_index(dataset, &index_settings, checker_iter);
     assert!(
@@ -179,6 +181,7 @@ pub fn rubber_custom_id(mut es: crate::ElasticSearchWrapper<'_>) {
     let index_settings = IndexSettings {
         nb_shards: 1,
         nb_replicas: 0,
+        ..Default::default()
     };
     let result = es
         .rubber
@@ -276,6 +279,7 @@ pub fn rubber_ghost_index_cleanup(mut es: crate::ElasticSearchWrapper<'_>) {
     let index_settings = IndexSettings {
         nb_shards: 1,
         nb_replicas: 0,
+        ..Default::default()
     };
     let result = es
         .rubber
@@ -326,3 +330,430 @@ pub fn rubber_empty_bulk(mut es: crate::ElasticSearchWrapper<'_>) {
     assert!(result.is_ok());
     assert_eq!(result.unwrap(), 0); // we have indexed nothing, but it's ok
 }
+
+pub fn rubber_incremental_index(mut es: crate::ElasticSearchWrapper<'_>) {
+    info!("running rubber_incremental_index");
+    let dataset = "my_dataset";
+    let make_street = |id: &str, name: &str| {
+        let coord = Coord::new(2.68326290f64, 48.5110722f64);
+        Street {
+            id: id.to_string(),
+            name: name.to_string(),
+            label: name.to_string(),
+            weight: 0.42,
+            coord: coord.clone(),
+            approx_coord: Some(coord.into()),
+            ..Default::default()
+        }
+    };
+    let index_settings = IndexSettings {
+        nb_shards: 1,
+        nb_replicas: 0,
+        ..Default::default()
+    };
+
+    // without any published index, we do a full import
+    let result = es.rubber.incremental_index(
+        dataset,
+        rubber::IndexVisibility::Public,
+        &index_settings,
+        vec![
+            make_street("bob", "bob's street"),
+            make_street("bobette", "bobette's street"),
+        ]
+        .into_iter(),
+    );
+    assert_eq!(
+        result.unwrap(),
+        rubber::IndexDelta {
+            nb_upserted: 2,
+            nb_unchanged: 0,
+            nb_deleted: 0,
+            nb_failed: 0,
+        }
+    );
+    es.refresh();
+    let indexes = get_munin_indexes(&es);
+    assert_eq!(indexes.len(), 1);
+
+    // bob is unchanged, bobette has disappeared and carl is new
+    let result = es.rubber.incremental_index(
+        dataset,
+        rubber::IndexVisibility::Public,
+        &index_settings,
+        vec![
+            make_street("bob", "bob's street"),
+            make_street("carl", "carl's street"),
+        ]
+        .into_iter(),
+    );
+    assert_eq!(
+        result.unwrap(),
+        rubber::IndexDelta {
+            nb_upserted: 1,
+            nb_unchanged: 1,
+            nb_deleted: 1,
+            nb_failed: 0,
+        }
+    );
+    es.refresh();
+
+    // the published index has been replaced by an updated copy
+    let new_indexes = get_munin_indexes(&es);
+    assert_eq!(new_indexes.len(), 1);
+    assert_ne!(new_indexes, indexes);
+    let ids: Vec<_> = es
+        .search_and_filter("*:*", |_| true)
+        .map(|p| match p {
+            mimir::Place::Street(s) => s.id,
+            _ => panic!("only streets should be indexed"),
+        })
+        .collect::<std::collections::BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    assert_eq!(ids, vec!["bob", "carl"]);
+
+    // the refresh of the new index has been restored
+    let index = &new_indexes[0];
+    let settings = &get_index_info(&es, index)[index]["settings"]["index"];
+    assert_eq!(settings["refresh_interval"], json!("1s"));
+
+    // the new index is not in the global indexes when the update is private
+    es.rubber
+        .incremental_index(
+            dataset,
+            rubber::IndexVisibility::Private,
+            &index_settings,
+            vec![
+                make_street("bob", "bob's street"),
+                make_street("carl", "carl's street"),
+            ]
+            .into_iter(),
+        )
+        .unwrap();
+    let indexes = get_munin_indexes(&es);
+    let index = &indexes[0];
+    let aliases = get_index_info(&es, index)[index]["aliases"]
+        .as_object()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect::<Vec<_>>();
+    assert_eq!(aliases, vec!["munin_street_my_dataset"]);
+}
+
+pub fn rubber_retention_and_rollback(mut es: crate::ElasticSearchWrapper<'_>) {
+    info!("running rubber_retention_and_rollback");
+    let dataset = "my_dataset";
+    let make_street = |id: &str| {
+        let coord = Coord::new(2.68326290f64, 48.5110722f64);
+        Street {
+            id: id.to_string(),
+            name: id.to_string(),
+            label: id.to_string(),
+            weight: 0.42,
+            coord: coord.clone(),
+            approx_coord: Some(coord.into()),
+            ..Default::default()
+        }
+    };
+    let index_settings = IndexSettings {
+        nb_shards: 1,
+        nb_replicas: 0,
+        nb_retained_indexes: 1,
+        ..Default::default()
+    };
+    let get_ids = |es: &crate::ElasticSearchWrapper<'_>| -> Vec<String> {
+        es.search_and_filter("*:*", |_| true)
+            .map(|p| match p {
+                mimir::Place::Street(s) => s.id,
+                _ => panic!("only streets should be indexed"),
+            })
+            .collect()
+    };
+
+    for id in &["first", "second", "third"] {
+        es.rubber
+            .public_index(dataset, &index_settings, std::iter::once(make_street(id)))
+            .unwrap();
+    }
+    es.refresh();
+
+    // only the previous index is kept, and it is not aliased
+    let indexes = get_munin_indexes(&es);
+    assert_eq!(indexes.len(), 2);
+    let previous_indexes = es.rubber.get_previous_indexes::<Street>(dataset).unwrap();
+    assert_eq!(previous_indexes.len(), 1);
+    assert!(indexes.contains(&previous_indexes[0]));
+    assert_eq!(get_ids(&es), vec!["third"]);
+
+    // we rollback to the previous index
+    let index = es.rubber.rollback_index::<Street>(dataset, None).unwrap();
+    assert_eq!(index, previous_indexes[0]);
+    es.refresh();
+    assert_eq!(get_ids(&es), vec!["second"]);
+    let aliases = get_index_info(&es, &index)[&index]["aliases"]
+        .as_object()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect::<Vec<_>>();
+    assert_eq!(
+        aliases,
+        vec![
+            "munin",
+            "munin_geo_data",
+            "munin_street",
+            "munin_street_my_dataset"
+        ]
+    );
+
+    // there is nothing older to rollback to
+    assert!(es.rubber.rollback_index::<Street>(dataset, None).is_err());
+
+    // the index published by the rollback is kept instead of the more recent one,
+    // and the indexes of the other datasets are not removed
+    es.rubber
+        .public_index(
+            "my_dataset-idf",
+            &index_settings,
+            std::iter::once(make_street("other")),
+        )
+        .unwrap();
+    let other_indexes = es
+        .rubber
+        .get_all_aliased_index("munin_street_my_dataset-idf")
+        .unwrap();
+    es.rubber
+        .public_index(
+            dataset,
+            &index_settings,
+            std::iter::once(make_street("fourth")),
+        )
+        .unwrap();
+    es.refresh();
+    assert_eq!(
+        es.rubber.get_previous_indexes::<Street>(dataset).unwrap(),
+        vec![index]
+    );
+    let indexes = get_munin_indexes(&es);
+    assert_eq!(indexes.len(), 3);
+    assert!(other_indexes.keys().all(|i| indexes.contains(i)));
+}
+
+pub fn rubber_publication_checks(mut es: crate::ElasticSearchWrapper<'_>) {
+    info!("running rubber_publication_checks");
+    let dataset = "my_dataset";
+    let make_street = |id: usize| {
+        let coord = Coord::new(2.68326290f64, 48.5110722f64);
+        Street {
+            id: format!("street:{}", id),
+            name: "bob's street".to_string(),
+            label: "bob's street".to_string(),
+            weight: 0.42,
+            coord: coord.clone(),
+            approx_coord: Some(coord.into()),
+            ..Default::default()
+        }
+    };
+    let mut index_settings = IndexSettings {
+        nb_shards: 1,
+        nb_replicas: 0,
+        ..Default::default()
+    };
+    index_settings.publication_checks.min_doc_count_ratio = Some(0.5);
+
+    let result = es
+        .rubber
+        .public_index(dataset, &index_settings, (0..10).map(make_street));
+    assert_eq!(result.unwrap(), 10);
+    es.refresh();
+    let indexes = get_munin_indexes(&es);
+    assert_eq!(indexes.len(), 1);
+
+    // a truncated import is not published, and the previous index is kept
+    let result = es
+        .rubber
+        .public_index(dataset, &index_settings, (0..2).map(make_street));
+    assert!(result.is_err());
+    es.refresh();
+    assert_eq!(get_munin_indexes(&es), indexes);
+    assert_eq!(es.count("munin_street", "*"), 10);
+
+    // the streets have no administrative regions
+    index_settings.publication_checks.max_no_admin_ratio = Some(0.1);
+    let result = es
+        .rubber
+        .public_index(dataset, &index_settings, (0..10).map(make_street));
+    assert!(result.is_err());
+    es.refresh();
+    assert_eq!(get_munin_indexes(&es), indexes);
+}
+
+pub fn rubber_bulk_rejected_documents(mut es: crate::ElasticSearchWrapper<'_>) {
+    info!("running rubber_bulk_rejected_documents");
+    // an index that rejects all the fields it does not know
+    let client = reqwest::Client::new();
+    let res = client
+        .put(&format!("{}/strict_streets", es.host()))
+        .body(
+            json!({"mappings": {"street": {
+                "dynamic": "strict",
+                "properties": {"id": {"type": "string"}}
+            }}})
+            .to_string(),
+        )
+        .send()
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    let dead_letter_file = std::env::temp_dir().join("mimir_rubber_dead_letters.jsonl");
+    let _ = std::fs::remove_file(&dead_letter_file);
+    es.rubber.bulk_settings = rubber::BulkSettings {
+        dead_letter_file: Some(dead_letter_file.clone()),
+        ..Default::default()
+    };
+
+    let make_street = |id: &str| Street {
+        id: id.to_string(),
+        name: "bob's street".to_string(),
+        ..Default::default()
+    };
+    let index = rubber::TypedIndex::<Street>::new("strict_streets".into());
+    let summary = es
+        .rubber
+        .bulk_index_with_summary(
+            &index,
+            vec![make_street("bob"), make_street("bobette")].into_iter(),
+        )
+        .unwrap();
+    // the documents are rejected, but the import does not stop
+    assert_eq!(
+        summary,
+        rubber::BulkSummary {
+            nb_indexed: 0,
+            nb_retried: 0,
+            nb_failed: 2,
+        }
+    );
+
+    // and the rejected documents are in the dead letter file, with their error
+    let dead_letters: Vec<Value> = std::fs::read_to_string(&dead_letter_file)
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(dead_letters.len(), 2);
+    assert_eq!(dead_letters[0]["index"], json!("strict_streets"));
+    assert_eq!(dead_letters[0]["status"], json!(400));
+    assert!(dead_letters[0]["error"].is_object());
+    assert_eq!(dead_letters[0]["document"]["id"], json!("bob"));
+    assert_eq!(dead_letters[1]["document"]["id"], json!("bobette"));
+    std::fs::remove_file(&dead_letter_file).unwrap();
+}
+
+pub fn rubber_concurrent_bulk(mut es: crate::ElasticSearchWrapper<'_>) {
+    info!("running rubber_concurrent_bulk");
+    let dataset = "my_dataset";
+    // small chunks sent by several requests at the same time
+    es.rubber.bulk_settings = rubber::BulkSettings {
+        chunk_size: 2,
+        nb_concurrent_requests: 3,
+        ..Default::default()
+    };
+    let index_settings = IndexSettings {
+        nb_shards: 1,
+        nb_replicas: 1,
+        ..Default::default()
+    };
+    let streets = (0..11).map(|i| Street {
+        id: format!("street:{}", i),
+        name: format!("street {}", i),
+        label: format!("street {}", i),
+        ..Default::default()
+    });
+
+    let index = es
+        .rubber
+        .make_index::<Street>(dataset, &index_settings)
+        .unwrap();
+    // while the index is loaded, its refresh and its replicas are disabled
+    let index_name = index.name().to_string();
+    let settings = &get_settings(&es, &index_name)[&index_name]["settings"]["index"];
+    assert_eq!(settings["refresh_interval"], json!("-1"));
+    assert_eq!(settings["number_of_replicas"], json!("0"));
+
+    let summary = es.rubber.bulk_index_with_summary(&index, streets).unwrap();
+    assert_eq!(summary.nb_indexed, 11);
+    assert_eq!(summary.nb_failed, 0);
+
+    es.rubber
+        .publish_index(
+            dataset,
+            index,
+            rubber::IndexVisibility::Public,
+            &index_settings,
+        )
+        .unwrap();
+    // they are restored on publication
+    let settings = &get_settings(&es, &index_name)[&index_name]["settings"]["index"];
+    assert_eq!(settings["refresh_interval"], json!("1s"));
+    assert_eq!(settings["number_of_replicas"], json!("1"));
+    assert_eq!(es.rubber.count_documents(&index_name).unwrap(), 11);
+}
+
+pub fn rubber_scroll(mut es: crate::ElasticSearchWrapper<'_>) {
+    info!("running rubber_scroll");
+    let dataset = "my_dataset";
+    let index_settings = IndexSettings {
+        nb_shards: 1,
+        nb_replicas: 0,
+        ..Default::default()
+    };
+    let streets = (0..11).map(|i| Street {
+        id: format!("street:{}", i),
+        name: format!("street {}", i),
+        label: format!("street {}", i),
+        ..Default::default()
+    });
+    es.rubber
+        .public_index(dataset, &index_settings, streets)
+        .unwrap();
+
+    // the streets are fetched lazily, by pages of 3 streets, without their name
+    let scroll_settings = rubber::ScrollSettings {
+        page_size: 3,
+        excluded_fields: vec!["name".to_string()],
+    };
+    let mut scroll = es
+        .rubber
+        .scroll_objects::<Street>("munin_street", &scroll_settings)
+        .unwrap();
+    let first = scroll.next().unwrap().unwrap();
+    assert!(first.id.starts_with("street:"));
+    assert_eq!(first.name, "");
+    let mut ids = scroll
+        .map(|street| street.unwrap().id)
+        .chain(std::iter::once(first.id))
+        .collect::<Vec<_>>();
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), 11);
+
+    // the whole index can still be fetched at once
+    let streets: Vec<Street> = es
+        .rubber
+        .get_all_objects_from_index("munin_street")
+        .unwrap();
+    assert_eq!(streets.len(), 11);
+    assert!(streets.iter().all(|s| s.name.starts_with("street ")));
+}
+
+fn get_settings(es: &crate::ElasticSearchWrapper<'_>, index: &str) -> Value {
+    let mut res = reqwest::Client::new()
+        .get(&format!("{}/{}/_settings", es.host(), index))
+        .send()
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    res.json().unwrap()
+}
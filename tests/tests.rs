@@ -47,6 +47,8 @@ mod bragi_synonyms_test;
 mod bragi_three_cities_test;
 mod canonical_import_process_test;
 mod cosmogony2mimir_test;
+mod embedded_test;
+mod mimir_dump_test;
 mod openaddresses2mimir_test;
 mod osm2mimir_bano2mimir_test;
 mod osm2mimir_test;
@@ -140,7 +142,14 @@ fn all_tests() {
     rubber_test::rubber_custom_id(ElasticSearchWrapper::new(&docker_wrapper));
     rubber_test::rubber_ghost_index_cleanup(ElasticSearchWrapper::new(&docker_wrapper));
     rubber_test::rubber_empty_bulk(ElasticSearchWrapper::new(&docker_wrapper));
+    rubber_test::rubber_incremental_index(ElasticSearchWrapper::new(&docker_wrapper));
+    rubber_test::rubber_retention_and_rollback(ElasticSearchWrapper::new(&docker_wrapper));
+    rubber_test::rubber_publication_checks(ElasticSearchWrapper::new(&docker_wrapper));
+    rubber_test::rubber_bulk_rejected_documents(ElasticSearchWrapper::new(&docker_wrapper));
+    rubber_test::rubber_concurrent_bulk(ElasticSearchWrapper::new(&docker_wrapper));
+    rubber_test::rubber_scroll(ElasticSearchWrapper::new(&docker_wrapper));
     bragi_bano_test::bragi_bano_test(ElasticSearchWrapper::new(&docker_wrapper));
+    embedded_test::bragi_embedded_bano_test();
     bragi_osm_test::bragi_osm_test(ElasticSearchWrapper::new(&docker_wrapper));
     bragi_poi_test::test_i18n_poi(ElasticSearchWrapper::new(&docker_wrapper));
     bragi_three_cities_test::bragi_three_cities_test(ElasticSearchWrapper::new(&docker_wrapper));
@@ -152,10 +161,12 @@ fn all_tests() {
     bragi_synonyms_test::bragi_synonyms_test(ElasticSearchWrapper::new(&docker_wrapper));
     openaddresses2mimir_test::oa2mimir_simple_test(ElasticSearchWrapper::new(&docker_wrapper));
     cosmogony2mimir_test::cosmogony2mimir_test(ElasticSearchWrapper::new(&docker_wrapper));
+    mimir_dump_test::mimir_dump_restore_test(ElasticSearchWrapper::new(&docker_wrapper));
     canonical_import_process_test::canonical_import_process_test(ElasticSearchWrapper::new(
         &docker_wrapper,
     ));
     canonical_import_process_test::bragi_invalid_es_test(ElasticSearchWrapper::new(
         &docker_wrapper,
     ));
+    canonical_import_process_test::bragi_failover_test(ElasticSearchWrapper::new(&docker_wrapper));
 }